@@ -0,0 +1,142 @@
+//! Matches between players.
+
+use std::io;
+use std::time::Duration;
+
+use crate::player::{Player, PlayerCommand, PlayerError};
+use crate::referee::{Game, ROBOTS_PER_PLAYER};
+
+/// Time the players have to answer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Timeouts {
+  pub first_turn: Duration,
+  pub turn: Duration,
+}
+
+impl Default for Timeouts {
+  /// The official timeouts.
+  fn default() -> Self {
+    Timeouts {
+      first_turn: Duration::from_millis(1000),
+      turn: Duration::from_millis(50),
+    }
+  }
+}
+
+/// A player deactivated during a match.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Deactivation {
+  pub turn: u32,
+  pub error: PlayerError,
+}
+
+/// Outcome of a match.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MatchResult {
+  pub scores: [u32; 2],
+  pub turns: u32,
+  pub deactivations: [Option<Deactivation>; 2],
+}
+
+impl MatchResult {
+  /// Final score of a player; a deactivated player ends with -1, as on CodinGame.
+  pub fn final_score(&self, player: usize) -> i64 {
+    if self.deactivations[player].is_some() {
+      -1
+    } else {
+      self.scores[player] as i64
+    }
+  }
+
+  /// Score difference from the point of view of a player.
+  pub fn score_delta(&self, player: usize) -> i64 {
+    self.final_score(player) - self.final_score(1 - player)
+  }
+
+  /// Winner of the match, if it’s not a draw.
+  pub fn winner(&self) -> Option<usize> {
+    match self.score_delta(0) {
+      delta if delta > 0 => Some(0),
+      delta if delta < 0 => Some(1),
+      _ => None
+    }
+  }
+}
+
+/// Play a game between two players.
+///
+/// Every turn, each player is given its own view of the game and must answer the actions of its
+/// robots in time. As with the official referee, a player timing out, crashing or answering an
+/// invalid action is deactivated: its robots stand still for the rest of the game, which goes on
+/// for the other player.
+pub fn play_match(mut game: Game, commands: [&PlayerCommand; 2], timeouts: Timeouts) -> io::Result<MatchResult> {
+  let mut players = [Player::spawn(commands[0])?, Player::spawn(commands[1])?];
+  let mut deactivations = [None, None];
+
+  for (player, process) in players.iter_mut().enumerate() {
+    if let Err(err) = process.send(&game.init_input()) {
+      deactivations[player] = Some(Deactivation { turn: 0, error: err.into() });
+    }
+  }
+
+  while !game.is_over() && deactivations.iter().any(Option::is_none) {
+    let timeout = if game.turn == 0 { timeouts.first_turn } else { timeouts.turn };
+    let mut actions = [Vec::new(), Vec::new()];
+
+    for (player, process) in players.iter_mut().enumerate() {
+      if deactivations[player].is_some() {
+        continue;
+      }
+
+      let answer = process.send(&game.input(player))
+        .map_err(PlayerError::from)
+        .and_then(|_| process.read_actions(ROBOTS_PER_PLAYER, timeout));
+
+      match answer {
+        Ok(player_actions) => actions[player] = player_actions,
+        Err(error) => deactivations[player] = Some(Deactivation { turn: game.turn, error }),
+      }
+    }
+
+    game.play_turn([&actions[0], &actions[1]]);
+  }
+
+  Ok(MatchResult {
+    scores: game.scores,
+    turns: game.turn,
+    deactivations,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::referee::MAX_TURNS;
+
+  /// Player waiting with all its robots, answering once per turn input (whose first line is the
+  /// only one made of two numbers, the initialization line aside).
+  fn waiting_player() -> PlayerCommand {
+    let script = "read init; while read a b c; do [ -n \"$b\" ] && [ -z \"$c\" ] && printf 'WAIT\\n%.0s' 1 2 3 4 5; done";
+
+    PlayerCommand {
+      program: "sh".to_owned(),
+      args: vec!["-c".to_owned(), script.to_owned()],
+      env: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn match_goes_on_without_a_deactivated_player() {
+    let crashing = PlayerCommand::parse("false").unwrap();
+    let timeouts = Timeouts { first_turn: Duration::from_secs(5), turn: Duration::from_secs(1) };
+
+    let result = play_match(Game::generate(0), [&waiting_player(), &crashing], timeouts).unwrap();
+
+    assert_eq!(result.deactivations[0], None);
+    assert_eq!(result.deactivations[1].as_ref().map(|deactivation| deactivation.turn), Some(0));
+    assert_eq!(result.turns, MAX_TURNS);
+    assert_eq!(result.scores, [0, 0]);
+    assert_eq!([result.final_score(0), result.final_score(1)], [0, -1]);
+    assert_eq!(result.winner(), Some(0));
+  }
+}
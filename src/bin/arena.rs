@@ -0,0 +1,120 @@
+//! Play matches between two player commands and sum the results up.
+//!
+//! ```text
+//! arena <command> <command> [--matches <n>] [--seed <n>] [--timeout <ms>] [--first-timeout <ms>]
+//!       [--size <width>x<height>] [--map <file>]
+//! ```
+//!
+//! Commands are whitespace-separated command lines, e.g. `"target/release/v04"` or
+//! `"target/release/codingame-unleash-the-geek prospect"`. Every match is played on a new map
+//! generated from the seed, of the standard size unless told otherwise, or on the map of a file to
+//! replay a given layout; players have the official time to answer unless told otherwise.
+
+use codingame_unleash_the_geek::arena::{play_match, Timeouts};
+use codingame_unleash_the_geek::map::Map;
+use codingame_unleash_the_geek::player::PlayerCommand;
+use codingame_unleash_the_geek::referee::{Game, HEIGHT, WIDTH};
+use std::process::exit;
+use std::time::Duration;
+
+struct Options {
+  commands: Vec<PlayerCommand>,
+  matches: u64,
+  seed: u64,
+  timeouts: Timeouts,
+  size: (usize, usize),
+  map: Option<Map>,
+}
+
+fn number<T>(arg: &str, value: &str) -> Result<T, String> where T: std::str::FromStr, T::Err: ToString {
+  value.parse().map_err(|err: T::Err| format!("{} {}: {}", arg, value, err.to_string()))
+}
+
+impl Options {
+  fn parse() -> Result<Self, String> {
+    let mut options = Options {
+      commands: Vec::new(),
+      matches: 10,
+      seed: 0,
+      timeouts: Timeouts::default(),
+      size: (WIDTH, HEIGHT),
+      map: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      if !arg.starts_with("--") {
+        options.commands.push(PlayerCommand::parse(&arg).ok_or("empty player command")?);
+        continue;
+      }
+
+      let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+
+      match arg.as_str() {
+        "--matches" => options.matches = number(&arg, &value)?,
+        "--seed" => options.seed = number(&arg, &value)?,
+        "--timeout" => options.timeouts.turn = Duration::from_millis(number(&arg, &value)?),
+        "--first-timeout" => options.timeouts.first_turn = Duration::from_millis(number(&arg, &value)?),
+        "--size" => {
+          let (width, height) = value.split_once('x').ok_or_else(|| format!("{} {}: expected <width>x<height>", arg, value))?;
+          options.size = (number(&arg, width)?, number(&arg, height)?);
+        }
+        "--map" => options.map = Some(Map::load(&value).map_err(|err| format!("{} {}: {}", arg, value, err))?),
+        _ => return Err(format!("unknown option {}", arg))
+      }
+    }
+
+    if options.commands.len() != 2 {
+      return Err("two player commands are expected".to_owned());
+    }
+
+    Ok(options)
+  }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+  let commands = [&options.commands[0], &options.commands[1]];
+  let mut wins = [0, 0];
+  let mut draws = 0;
+  let mut totals = [0, 0];
+
+  for seed in options.seed .. options.seed + options.matches {
+    let map = options.map.clone().unwrap_or_else(|| Map::generate(options.size.0, options.size.1, seed));
+    let result = play_match(Game::on_map(map, seed), commands, options.timeouts).map_err(|err| err.to_string())?;
+
+    print!("map {}: {} - {} in {} turns", seed, result.final_score(0), result.final_score(1), result.turns);
+    for (player, deactivation) in result.deactivations.iter().enumerate() {
+      if let Some(deactivation) = deactivation {
+        print!(", player {} deactivated at turn {} ({})", player, deactivation.turn, deactivation.error);
+      }
+    }
+    println!();
+
+    match result.winner() {
+      Some(winner) => wins[winner] += 1,
+      None => draws += 1,
+    }
+
+    for (player, total) in totals.iter_mut().enumerate() {
+      *total += result.final_score(player);
+    }
+  }
+
+  let matches = options.matches.max(1) as f64;
+  for player in 0 .. 2 {
+    println!("player {} ({}): {} win(s), mean score {:.2}", player, commands[player], wins[player], totals[player] as f64 / matches);
+  }
+  println!("{} draw(s)", draws);
+
+  Ok(())
+}
+
+fn main() {
+  let result = Options::parse().and_then(|options| run(&options));
+
+  if let Err(err) = result {
+    eprintln!("{}", err);
+    exit(1);
+  }
+}
@@ -0,0 +1,10 @@
+//! Reference bot mining the closest ore, without any item.
+
+use codingame_unleash_the_geek::bots::{greedy, run};
+
+fn main() {
+  run(|view| {
+    let mut claimed = Vec::new();
+    view.robots().map(|robot| greedy(view, robot, &mut claimed)).collect()
+  });
+}
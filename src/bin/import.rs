@@ -0,0 +1,455 @@
+//! Turn what the bot printed during an online match into files it can be run on again locally.
+//!
+//! ```text
+//! import (--stderr <file> | --json <file>) [--stdout <file>] [--agent <index>] --out <prefix>
+//! ```
+//!
+//! The turn inputs come from the replay the bot dumps on stderr when `REPLAY_DUMP` is set: either
+//! its stderr copied from the CodinGame console (`--stderr`, other debug output being ignored), or
+//! the game JSON downloaded from CodinGame (`--json`), in which case the stderr and stdout of the
+//! agent that dumped a replay are used, unless `--agent` says which one is ours. The actions the
+//! bot answered are taken from `--stdout` or the game JSON if given, from the replay otherwise.
+//!
+//! Three files are written:
+//!
+//! - `<prefix>.in`, every input of the match, to be piped into the bot, e.g.
+//!   `target/release/codingame-unleash-the-geek < <prefix>.in`;
+//! - `<prefix>.out`, what the bot answered online, to compare with what it answers now;
+//! - `<prefix>.replay`, to be turned into a page with `viz`.
+
+use codingame_unleash_the_geek::replay::Replay;
+use std::fs;
+use std::process::exit;
+
+/// A minimal JSON parser, enough to read the game files CodinGame serves.
+mod json {
+  /// A JSON value.
+  #[derive(Clone, Debug, PartialEq)]
+  pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+  }
+
+  impl Json {
+    /// Value of a key, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+      match *self {
+        Json::Object(ref entries) => entries.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+        _ => None
+      }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+      match *self {
+        Json::String(ref s) => Some(s),
+        _ => None
+      }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+      match *self {
+        Json::Number(n) => Some(n),
+        _ => None
+      }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+      match *self {
+        Json::Array(ref values) => Some(values),
+        _ => None
+      }
+    }
+  }
+
+  /// Parse a JSON document.
+  pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0 };
+    let value = parser.value()?;
+
+    parser.skip_whitespace();
+    if parser.pos != parser.chars.len() {
+      return Err(parser.error("trailing characters"));
+    }
+
+    Ok(value)
+  }
+
+  struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+  }
+
+  impl Parser {
+    fn error(&self, msg: &str) -> String {
+      format!("invalid JSON at character {}: {}", self.pos, msg)
+    }
+
+    fn peek(&self) -> Option<char> {
+      self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+      let c = self.peek();
+      self.pos += 1;
+      c
+    }
+
+    fn skip_whitespace(&mut self) {
+      while self.peek().map(char::is_whitespace).unwrap_or(false) {
+        self.pos += 1;
+      }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+      self.skip_whitespace();
+
+      match self.next() {
+        Some(c) if c == expected => Ok(()),
+        _ => Err(self.error(&format!("expected {}", expected)))
+      }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+      for expected in keyword.chars() {
+        if self.next() != Some(expected) {
+          return Err(self.error(&format!("expected {}", keyword)));
+        }
+      }
+
+      Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+      self.skip_whitespace();
+
+      match self.peek() {
+        Some('{') => self.object(),
+        Some('[') => self.array(),
+        Some('"') => self.string().map(Json::String),
+        Some('t') => self.keyword("true", Json::Bool(true)),
+        Some('f') => self.keyword("false", Json::Bool(false)),
+        Some('n') => self.keyword("null", Json::Null),
+        Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+        _ => Err(self.error("expected a value"))
+      }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+      let mut entries = Vec::new();
+      self.expect('{')?;
+      self.skip_whitespace();
+
+      if self.peek() == Some('}') {
+        self.pos += 1;
+        return Ok(Json::Object(entries));
+      }
+
+      loop {
+        self.skip_whitespace();
+        let key = self.string()?;
+        self.expect(':')?;
+        entries.push((key, self.value()?));
+        self.skip_whitespace();
+
+        match self.next() {
+          Some(',') => continue,
+          Some('}') => return Ok(Json::Object(entries)),
+          _ => return Err(self.error("expected , or }"))
+        }
+      }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+      let mut values = Vec::new();
+      self.expect('[')?;
+      self.skip_whitespace();
+
+      if self.peek() == Some(']') {
+        self.pos += 1;
+        return Ok(Json::Array(values));
+      }
+
+      loop {
+        values.push(self.value()?);
+        self.skip_whitespace();
+
+        match self.next() {
+          Some(',') => continue,
+          Some(']') => return Ok(Json::Array(values)),
+          _ => return Err(self.error("expected , or ]"))
+        }
+      }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+      let digits = self.chars.get(self.pos .. self.pos + 4).ok_or_else(|| self.error("truncated escape"))?.iter().collect::<String>();
+      self.pos += 4;
+      u32::from_str_radix(&digits, 16).map_err(|_| self.error("invalid escape"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+      let mut s = String::new();
+      self.expect('"')?;
+
+      loop {
+        match self.next() {
+          Some('"') => return Ok(s),
+
+          Some('\\') => match self.next() {
+            Some('n') => s.push('\n'),
+            Some('t') => s.push('\t'),
+            Some('r') => s.push('\r'),
+            Some('b') => s.push('\u{8}'),
+            Some('f') => s.push('\u{c}'),
+            Some('u') => {
+              let mut code = self.hex4()?;
+
+              // characters outside of the basic plane come as surrogate pairs
+              if (0xd800 .. 0xdc00).contains(&code) && self.chars.get(self.pos .. self.pos + 2) == Some(&['\\', 'u']) {
+                let pos = self.pos;
+                self.pos += 2;
+
+                match self.hex4()? {
+                  low @ 0xdc00 ..= 0xdfff => code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00),
+                  _ => self.pos = pos
+                }
+              }
+
+              s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            Some(c) => s.push(c),
+            None => return Err(self.error("unterminated string"))
+          },
+
+          Some(c) => s.push(c),
+          None => return Err(self.error("unterminated string"))
+        }
+      }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+      let start = self.pos;
+
+      while self.peek().map(|c| c.is_ascii_digit() || "+-.eE".contains(c)).unwrap_or(false) {
+        self.pos += 1;
+      }
+
+      self.chars[start .. self.pos].iter().collect::<String>().parse().map(Json::Number).map_err(|_| self.error("invalid number"))
+    }
+  }
+}
+
+struct Options {
+  stderr: Option<String>,
+  stdout: Option<String>,
+  json: Option<String>,
+  agent: Option<usize>,
+  out: String,
+}
+
+impl Options {
+  fn parse() -> Result<Self, String> {
+    let mut options = Options {
+      stderr: None,
+      stdout: None,
+      json: None,
+      agent: None,
+      out: String::new(),
+    };
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+
+      match arg.as_str() {
+        "--stderr" => options.stderr = Some(value),
+        "--stdout" => options.stdout = Some(value),
+        "--json" => options.json = Some(value),
+        "--agent" => options.agent = Some(value.parse().map_err(|err| format!("{} {}: {}", arg, value, err))?),
+        "--out" => options.out = value,
+        _ => return Err(format!("unknown option {}", arg))
+      }
+    }
+
+    if options.out.is_empty() {
+      return Err("usage: import (--stderr <file> | --json <file>) [--stdout <file>] [--agent <index>] --out <prefix>".to_owned());
+    }
+
+    if options.stderr.is_some() == options.json.is_some() {
+      return Err("either --stderr or --json is needed, not both".to_owned());
+    }
+
+    Ok(options)
+  }
+}
+
+fn read(path: &str) -> Result<String, String> {
+  fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Frames of a game JSON: `gameResult.frames` in the JSON CodinGame serves for a game, or `frames`
+/// in the game result alone.
+fn frames(game: &json::Json) -> Option<&[json::Json]> {
+  game.get("gameResult").unwrap_or(game).get("frames").and_then(|frames| frames.as_array())
+}
+
+/// Stderr and stdout of an agent of a game JSON, frame after frame.
+///
+/// Without an agent given, ours is the one that dumped a replay.
+fn agent_streams(game: &json::Json, agent: Option<usize>) -> Result<(String, String), String> {
+  let frames = frames(game).ok_or("no frames in the game JSON")?;
+
+  let streams = |agent: usize| {
+    let mut stderr = String::new();
+    let mut stdout = String::new();
+
+    for frame in frames.iter().filter(|frame| frame.get("agentId").and_then(|id| id.as_f64()) == Some(agent as f64)) {
+      for (stream, key) in [(&mut stderr, "stderr"), (&mut stdout, "stdout")] {
+        if let Some(text) = frame.get(key).and_then(|text| text.as_str()) {
+          *stream += text;
+
+          if !text.ends_with('\n') {
+            stream.push('\n');
+          }
+        }
+      }
+    }
+
+    (stderr, stdout)
+  };
+
+  match agent {
+    Some(agent) => Ok(streams(agent)),
+    None => (0 .. 2).map(streams).find(|(stderr, _)| stderr.lines().any(|line| line.starts_with("@<")))
+      .ok_or_else(|| "no agent dumped a replay; was REPLAY_DUMP set?".to_owned())
+  }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+  let mut stderr = String::new();
+  let mut stdout = None;
+
+  if let Some(ref path) = options.json {
+    let game = json::parse(&read(path)?).map_err(|err| format!("{}: {}", path, err))?;
+    let (agent_stderr, agent_stdout) = agent_streams(&game, options.agent)?;
+
+    stderr = agent_stderr;
+    stdout = Some(agent_stdout).filter(|stdout| !stdout.trim().is_empty());
+  }
+
+  if let Some(ref path) = options.stderr {
+    stderr = read(path)?;
+  }
+
+  if let Some(ref path) = options.stdout {
+    stdout = Some(read(path)?);
+  }
+
+  let mut replay = Replay::parse(&stderr).map_err(|err| format!("cannot read the replay: {}", err))?;
+
+  if replay.turns.is_empty() {
+    return Err("no turn found; was REPLAY_DUMP set?".to_owned());
+  }
+
+  // what the bot actually answered online prevails over what it recorded
+  if let Some(stdout) = stdout {
+    let mut lines = stdout.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    for turn in &mut replay.turns {
+      let robots = turn.view.robots().count();
+      turn.outputs = lines.by_ref().take(robots).map(str::to_owned).collect();
+    }
+  }
+
+  let write = |extension: &str, content: &str| {
+    let path = format!("{}.{}", options.out, extension);
+    fs::write(&path, content).map_err(|err| format!("{}: {}", path, err))
+  };
+
+  write("in", &replay.input())?;
+  write("out", &replay.output())?;
+  write("replay", &replay.to_string())?;
+  println!("{} turn(s) written to {}.{{in,out,replay}}", replay.turns.len(), options.out);
+
+  Ok(())
+}
+
+fn main() {
+  let result = Options::parse().and_then(|options| run(&options));
+
+  if let Err(err) = result {
+    eprintln!("{}", err);
+    exit(1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use json::Json;
+
+  #[test]
+  fn json_values() {
+    let game = json::parse(r#" {"a": [1, -2.5e1, true, false, null], "b": {"c": "d"}} "#).unwrap();
+
+    assert_eq!(game.get("a"), Some(&Json::Array(vec![Json::Number(1.), Json::Number(-25.), Json::Bool(true), Json::Bool(false), Json::Null])));
+    assert_eq!(game.get("b").and_then(|b| b.get("c")).and_then(Json::as_str), Some("d"));
+    assert_eq!(game.get("c"), None);
+    assert!(json::parse("[1, 2").is_err());
+    assert!(json::parse("[1] 2").is_err());
+  }
+
+  #[test]
+  fn json_escapes() {
+    let parsed = json::parse(r#""a\"b\\c\/d\n\t\r\b\fé""#).unwrap();
+
+    assert_eq!(parsed, Json::String("a\"b\\c/d\n\t\r\u{8}\u{c}é".to_owned()));
+    assert!(json::parse(r#""\u00g9""#).is_err());
+    assert!(json::parse(r#""\u00"#).is_err());
+  }
+
+  #[test]
+  fn json_surrogate_pairs() {
+    assert_eq!(json::parse(r#""\ud83d\ude00!""#), Ok(Json::String("\u{1f600}!".to_owned())));
+
+    // a lone surrogate can’t be a character
+    assert_eq!(json::parse(r#""\ud83d""#), Ok(Json::String("\u{fffd}".to_owned())));
+    assert_eq!(json::parse(r#""\ud83d\u0041""#), Ok(Json::String("\u{fffd}A".to_owned())));
+  }
+
+  #[test]
+  fn agent_streams_pick_the_replay() {
+    let game = json::parse(r#"{"frames": [
+      {"agentId": 0, "stdout": "WAIT", "stderr": "thinking"},
+      {"agentId": 1, "stdout": "MOVE 1 2\n", "stderr": "@<30 15"},
+      {"agentId": 0, "stdout": "WAIT"},
+      {"agentId": 1, "stdout": "DIG 2 2", "stderr": "debug\n@>DIG 2 2"}
+    ]}"#).unwrap();
+
+    let streams = |stderr: &str, stdout: &str| Ok((stderr.to_owned(), stdout.to_owned()));
+
+    assert_eq!(agent_streams(&game, None), streams("@<30 15\ndebug\n@>DIG 2 2\n", "MOVE 1 2\nDIG 2 2\n"));
+    assert_eq!(agent_streams(&game, Some(0)), streams("thinking\n", "WAIT\nWAIT\n"));
+
+    let game = json::parse(r#"{"frames": [{"agentId": 0, "stderr": "debug"}]}"#).unwrap();
+    assert!(agent_streams(&game, None).is_err());
+  }
+
+  #[test]
+  fn frames_are_looked_up_by_their_path() {
+    let game = json::parse(r#"{
+      "viewer": {"frames": [{"agentId": 0, "stderr": "@<decoy"}]},
+      "gameResult": {"frames": [{"agentId": 1, "stderr": "@<30 15"}]}
+    }"#).unwrap();
+
+    assert_eq!(agent_streams(&game, None), Ok(("@<30 15\n".to_owned(), String::new())));
+
+    let game = json::parse(r#"{"viewer": {"frames": [{"agentId": 0, "stderr": "@<decoy"}]}}"#).unwrap();
+    assert!(agent_streams(&game, None).is_err());
+  }
+}
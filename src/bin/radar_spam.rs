@@ -0,0 +1,41 @@
+//! Reference bot burrying a radar every time one is available, mining greedily otherwise.
+
+use codingame_unleash_the_geek::bots::{first_requester, greedy, run};
+use codingame_unleash_the_geek::referee::{Action, Item, RADAR_RANGE};
+use codingame_unleash_the_geek::view::{EntityKind, PlayerView};
+
+/// Next place to burry a radar in: the first spot of a grid covering the map without one of ours.
+fn radar_spot(view: &PlayerView, claimed: &[[i32; 2]]) -> Option<[i32; 2]> {
+  let step = RADAR_RANGE as usize + 1;
+
+  (step .. view.width).step_by(step)
+    .flat_map(|x| (step / 2 .. view.height).step_by(step).map(move |y| [x as i32, y as i32]))
+    .find(|&[x, y]| {
+      !claimed.contains(&[x, y]) && !view.entities_of(EntityKind::Radar).any(|radar| [radar.x, radar.y] == [x, y])
+    })
+}
+
+fn main() {
+  run(|view| {
+    let mut claimed = Vec::new();
+
+    view.robots().map(|robot| {
+      if robot.is_dead() {
+        Action::Wait
+      } else if robot.item == Some(Item::Radar) {
+        match radar_spot(view, &claimed) {
+          Some([x, y]) => {
+            claimed.push([x, y]);
+            Action::Dig(x, y)
+          }
+
+          None => Action::Dig(robot.x + 1, robot.y)
+        }
+      } else if first_requester(view, robot, Item::Radar, |_, _| true) {
+        Action::Request(Item::Radar)
+      } else {
+        greedy(view, robot, &mut claimed)
+      }
+    }).collect()
+  });
+}
@@ -0,0 +1,22 @@
+//! Reference bot moving and digging at random.
+
+use codingame_unleash_the_geek::bots::run;
+use codingame_unleash_the_geek::referee::Action;
+use rand::{Rng, thread_rng};
+
+fn main() {
+  let mut rng = thread_rng();
+
+  run(|view| {
+    view.robots().map(|robot| {
+      if robot.is_dead() {
+        return Action::Wait;
+      }
+
+      let x = rng.gen_range(0, view.width as i32);
+      let y = rng.gen_range(0, view.height as i32);
+
+      if rng.gen::<bool>() { Action::Move(x, y) } else { Action::Dig(x, y) }
+    }).collect()
+  });
+}
@@ -0,0 +1,54 @@
+//! Reference bot trapping the holes next to the opponent robots every time a trap is available.
+//!
+//! The first robot burries a few radars; the others mine greedily when they’re not trapping.
+
+use codingame_unleash_the_geek::bots::{first_requester, greedy, is_live_opponent, run};
+use codingame_unleash_the_geek::referee::{manh_dist, Action, Item};
+use codingame_unleash_the_geek::view::{EntityKind, PlayerView};
+
+/// Places the first robot burries radars at, in order.
+const RADARS: &[[i32; 2]] = &[[6, 4], [6, 10], [12, 4], [12, 10]];
+
+/// Hole closest to an opponent robot, which is probably mining there.
+fn trap_spot(view: &PlayerView, claimed: &[[i32; 2]]) -> Option<[i32; 2]> {
+  let opponents = view.entities.iter().filter(|entity| is_live_opponent(entity)).collect::<Vec<_>>();
+
+  (1 .. view.width as i32)
+    .flat_map(|x| (0 .. view.height as i32).map(move |y| [x, y]))
+    .filter(|&[x, y]| view.hole_at(x, y) && !claimed.contains(&[x, y]))
+    .filter(|&[x, y]| !view.entities_of(EntityKind::Trap).any(|trap| [trap.x, trap.y] == [x, y]))
+    .min_by_key(|&cell| opponents.iter().map(|opponent| manh_dist([opponent.x, opponent.y], cell)).min().unwrap_or(i32::MAX))
+}
+
+fn main() {
+  run(|view| {
+    let mut claimed = Vec::new();
+    let radars = view.entities_of(EntityKind::Radar).count();
+
+    view.robots().enumerate().map(|(index, robot)| {
+      let from = [robot.x, robot.y];
+
+      if robot.is_dead() {
+        Action::Wait
+      } else if robot.item == Some(Item::Radar) {
+        let [x, y] = RADARS[radars.min(RADARS.len() - 1)];
+        Action::Dig(x, y)
+      } else if robot.item == Some(Item::Trap) {
+        match trap_spot(view, &claimed) {
+          Some([x, y]) => {
+            claimed.push([x, y]);
+            Action::Dig(x, y)
+          }
+
+          None => Action::Dig(from[0] + 1, from[1])
+        }
+      } else if index == 0 && radars < RADARS.len() && robot.x == 0 && view.radar_cooldown == 0 {
+        Action::Request(Item::Radar)
+      } else if first_requester(view, robot, Item::Trap, |index, _| index != 0) {
+        Action::Request(Item::Trap)
+      } else {
+        greedy(view, robot, &mut claimed)
+      }
+    }).collect()
+  });
+}
@@ -0,0 +1,337 @@
+//! Tune the bot parameters with a genetic algorithm.
+//!
+//! Every candidate is a set of parameters, given to the bot through its `UTG_<KEY>` environment
+//! variables, and evaluated by playing offline matches against a fixed pool of opponents. The
+//! fitness of a candidate is its mean score difference.
+//!
+//! ```text
+//! tune [--bot <command>] [--opponent <command>]... [--population <n>] [--generations <n>]
+//!      [--matches <n>] [--seed <n>] [--threads <n>] [--timeout <ms>] [--out <path>]
+//!      [--history <path>]
+//! ```
+//!
+//! The bot defaults to the `codingame-unleash-the-geek` executable next to this one and the
+//! opponents to that same bot playing its default parameters and its `prospect` strategy. The best
+//! parameters are written as a file `UTG_PARAMS` can point to, and the fitness history as CSV.
+//!
+//! Several matches are played at once, so players get more time to answer than online: the
+//! official time for the first turn and `TURN_TIMEOUT` for the others, unless `--timeout` gives
+//! another one for every turn. Tune with release builds (`cargo build --release`); debug builds of
+//! the bot are too slow to answer in time.
+
+use codingame_unleash_the_geek::arena::{play_match, Timeouts};
+use codingame_unleash_the_geek::player::PlayerCommand;
+use codingame_unleash_the_geek::referee::Game;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::fs;
+use std::process::exit;
+use std::thread;
+use std::time::Duration;
+
+/// A tunable parameter of the bot.
+struct Gene {
+  name: &'static str,
+  min: f64,
+  max: f64,
+  default: f64,
+  integer: bool,
+}
+
+/// Parameters we tune, with the bounds we search them in; defaults are the bot’s constants.
+const GENES: &[Gene] = &[
+  Gene { name: "min_ore_no_radar_needed", min: 0., max: 40., default: 10., integer: true },
+  Gene { name: "max_random_safe_dest_tries", min: 1., max: 20., default: 10., integer: true },
+  Gene { name: "exploration_ratio_threshold", min: 0.2, max: 1., default: 0.6, integer: false },
+  Gene { name: "exploration_start_min_distance", min: 1., max: 10., default: 3., integer: true },
+  Gene { name: "exploration_delta_distance", min: 1., max: 10., default: 5., integer: true },
+  Gene { name: "max_radars", min: 0., max: 20., default: 10., integer: true },
+  Gene { name: "scarce_ore_amount", min: 0., max: 40., default: 15., integer: true },
+  Gene { name: "max_traps", min: 0., max: 15., default: 5., integer: true },
+  Gene { name: "race_distance_bonus", min: 0., max: 10., default: 4., integer: true },
+  Gene { name: "radar_likely", min: 0.5, max: 0.99, default: 0.6, integer: false },
+  Gene { name: "danger_exposure_turns", min: 0., max: 6., default: 2., integer: true },
+];
+
+/// Probability for a gene to mutate.
+const MUTATION_RATE: f64 = 0.2;
+
+/// Standard deviation of a mutation, relatively to the range of the gene.
+const MUTATION_SCALE: f64 = 0.15;
+
+/// Number of candidates competing to be a parent.
+const TOURNAMENT_SIZE: usize = 3;
+
+/// Number of best candidates kept as they are in the next generation.
+const ELITES: usize = 2;
+
+/// Time players have to answer a turn but the first one, unless told otherwise.
+const TURN_TIMEOUT: Duration = Duration::from_millis(200);
+
+type Genome = Vec<f64>;
+
+struct Options {
+  bot: PlayerCommand,
+  opponents: Vec<PlayerCommand>,
+  population: usize,
+  generations: usize,
+  matches: usize,
+  seed: u64,
+  threads: usize,
+  timeouts: Timeouts,
+  out: String,
+  history: String,
+}
+
+impl Options {
+  fn parse() -> Result<Self, String> {
+    let default_bot = std::env::current_exe()
+      .map_err(|err| err.to_string())?
+      .with_file_name("codingame-unleash-the-geek")
+      .to_string_lossy()
+      .into_owned();
+
+    let mut options = Options {
+      bot: PlayerCommand::parse(&default_bot).unwrap(),
+      opponents: Vec::new(),
+      population: 16,
+      generations: 20,
+      matches: 4,
+      seed: 0,
+      threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+      timeouts: Timeouts { turn: TURN_TIMEOUT, ..Timeouts::default() },
+      out: "best.params".to_owned(),
+      history: "fitness.csv".to_owned(),
+    };
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+      let number = |value: &str| value.parse::<usize>().map_err(|err| format!("{} {}: {}", arg, value, err));
+
+      match arg.as_str() {
+        "--bot" => options.bot = PlayerCommand::parse(&value).ok_or("empty bot command")?,
+        "--opponent" => options.opponents.push(PlayerCommand::parse(&value).ok_or("empty opponent command")?),
+        "--population" => options.population = number(&value)?.max(ELITES + 1),
+        "--generations" => options.generations = number(&value)?,
+        "--matches" => options.matches = number(&value)?.max(1),
+        "--seed" => options.seed = number(&value)? as u64,
+        "--threads" => options.threads = number(&value)?.max(1),
+        "--timeout" => {
+          let timeout = Duration::from_millis(number(&value)? as u64);
+          options.timeouts = Timeouts { first_turn: timeout.max(options.timeouts.first_turn), turn: timeout };
+        }
+        "--out" => options.out = value,
+        "--history" => options.history = value,
+        _ => return Err(format!("unknown option {}", arg))
+      }
+    }
+
+    if options.opponents.is_empty() {
+      options.opponents.push(options.bot.clone());
+
+      let mut prospect = options.bot.clone();
+      prospect.args.push("prospect".to_owned());
+      options.opponents.push(prospect);
+    }
+
+    Ok(options)
+  }
+}
+
+/// Value of a gene as the bot parses it.
+fn gene_value(gene: &Gene, value: f64) -> String {
+  if gene.integer {
+    format!("{}", value.round() as i64)
+  } else {
+    format!("{:.3}", value)
+  }
+}
+
+/// Bot command playing a genome.
+fn genome_command(bot: &PlayerCommand, genome: &[f64]) -> PlayerCommand {
+  GENES.iter().zip(genome).fold(bot.clone(), |command, (gene, &value)| {
+    command.with_env(format!("UTG_{}", gene.name.to_uppercase()), gene_value(gene, value))
+  })
+}
+
+/// Mean score difference of a genome against the opponents, on the maps of a generation.
+///
+/// The candidate alternates seats from one match to the next: digs are resolved in player order,
+/// so always playing first would bias its fitness.
+fn evaluate(options: &Options, genome: &[f64], maps: &[u64]) -> Result<f64, String> {
+  let command = genome_command(&options.bot, genome);
+  let mut total = 0;
+  let mut count = 0;
+
+  for opponent in &options.opponents {
+    for (i, &map) in maps.iter().enumerate() {
+      let seat = i % 2;
+      let commands = if seat == 0 { [&command, opponent] } else { [opponent, &command] };
+      let result = play_match(Game::generate(map), commands, options.timeouts).map_err(|err| format!("{}: {}", opponent, err))?;
+
+      if let Some(ref deactivation) = result.deactivations[seat] {
+        eprintln!("the bot failed against {} at turn {}: {}", opponent, deactivation.turn, deactivation.error);
+      }
+
+      total += result.score_delta(seat);
+      count += 1;
+    }
+  }
+
+  Ok(total as f64 / count as f64)
+}
+
+/// Evaluate a whole population, spreading the candidates on several threads.
+fn evaluate_all(options: &Options, population: &[Genome], maps: &[u64]) -> Result<Vec<f64>, String> {
+  let chunk_size = population.len().div_ceil(options.threads);
+
+  thread::scope(|scope| {
+    let handles = population.chunks(chunk_size).map(|chunk| {
+      scope.spawn(move || chunk.iter().map(|genome| evaluate(options, genome, maps)).collect::<Result<Vec<_>, _>>())
+    }).collect::<Vec<_>>();
+
+    let mut fitnesses = Vec::with_capacity(population.len());
+
+    for handle in handles {
+      fitnesses.extend(handle.join().map_err(|_| "an evaluation thread panicked".to_owned())??);
+    }
+
+    Ok(fitnesses)
+  })
+}
+
+/// Normally distributed number (Box-Muller).
+fn gaussian<R>(rng: &mut R) -> f64 where R: Rng {
+  let u = rng.gen_range(f64::EPSILON, 1.);
+  let v = rng.gen_range(0., 1.);
+
+  (-2. * u.ln()).sqrt() * (2. * std::f64::consts::PI * v).cos()
+}
+
+/// Pick a parent among a few random candidates.
+fn tournament<'a, R>(rng: &mut R, population: &'a [Genome], fitnesses: &[f64]) -> &'a Genome where R: Rng {
+  let best = (0 .. TOURNAMENT_SIZE)
+    .map(|_| rng.gen_range(0, population.len()))
+    .max_by(|&a, &b| fitnesses[a].partial_cmp(&fitnesses[b]).unwrap())
+    .unwrap();
+
+  &population[best]
+}
+
+/// Child of two parents: uniform crossover, then gaussian mutations within the gene bounds.
+fn offspring<R>(rng: &mut R, a: &[f64], b: &[f64]) -> Genome where R: Rng {
+  GENES.iter().zip(a.iter().zip(b)).map(|(gene, (&a, &b))| {
+    let mut value = if rng.gen::<bool>() { a } else { b };
+
+    if rng.gen::<f64>() < MUTATION_RATE {
+      value += gaussian(rng) * MUTATION_SCALE * (gene.max - gene.min);
+    }
+
+    value.max(gene.min).min(gene.max)
+  }).collect()
+}
+
+/// Parameters file of a genome.
+fn params_file(genome: &[f64], fitness: f64) -> String {
+  let mut content = format!("# mean score difference: {:.2}\n", fitness);
+
+  for (gene, &value) in GENES.iter().zip(genome) {
+    content += &format!("{} = {}\n", gene.name, gene_value(gene, value));
+  }
+
+  content
+}
+
+fn run(options: &Options) -> Result<(), String> {
+  let mut rng = StdRng::seed_from_u64(options.seed);
+  let write = |path: &str, content: &str| fs::write(path, content).map_err(|err| format!("{}: {}", path, err));
+
+  // the defaults are part of the first generation, so that we can only do better
+  let mut population = vec![GENES.iter().map(|gene| gene.default).collect::<Genome>()];
+  while population.len() < options.population {
+    population.push(GENES.iter().map(|gene| rng.gen_range(gene.min, gene.max)).collect());
+  }
+
+  let mut history = "generation,best,mean\n".to_owned();
+
+  for generation in 0 .. options.generations.max(1) {
+    // every generation plays new maps, all its candidates playing the same ones
+    let maps = (0 .. options.matches).map(|_| rng.gen()).collect::<Vec<u64>>();
+    let fitnesses = evaluate_all(options, &population, &maps)?;
+
+    let mut ranking = (0 .. population.len()).collect::<Vec<_>>();
+    ranking.sort_by(|&a, &b| fitnesses[b].partial_cmp(&fitnesses[a]).unwrap());
+
+    let best = ranking[0];
+    let mean = fitnesses.iter().sum::<f64>() / fitnesses.len() as f64;
+
+    eprintln!("generation {}: best {:.2}, mean {:.2}", generation, fitnesses[best], mean);
+    history += &format!("{},{:.3},{:.3}\n", generation, fitnesses[best], mean);
+    write(&options.history, &history)?;
+    write(&options.out, &params_file(&population[best], fitnesses[best]))?;
+
+    let mut next = ranking.iter().take(ELITES).map(|&i| population[i].clone()).collect::<Vec<_>>();
+    while next.len() < options.population {
+      let a = tournament(&mut rng, &population, &fitnesses);
+      let b = tournament(&mut rng, &population, &fitnesses);
+      next.push(offspring(&mut rng, a, b));
+    }
+
+    population = next;
+  }
+
+  Ok(())
+}
+
+fn main() {
+  let result = Options::parse().and_then(|options| run(&options));
+
+  if let Err(err) = result {
+    eprintln!("{}", err);
+    exit(1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Value of a constant of the bot, as written in its source.
+  fn bot_constant(source: &str, name: &str) -> Option<f64> {
+    let prefix = format!("const {}:", name);
+    let line = source.lines().find(|line| line.starts_with(&prefix))?;
+    let value = line.split('=').nth(1)?.trim().trim_end_matches(';');
+
+    value.parse().ok()
+  }
+
+  #[test]
+  fn gene_defaults_are_the_bot_constants() {
+    let source = include_str!("../main.rs");
+
+    for gene in GENES {
+      let constant = bot_constant(source, &gene.name.to_uppercase());
+      assert_eq!(constant, Some(gene.default), "{}", gene.name);
+    }
+  }
+
+  #[test]
+  fn genes_stay_in_their_bounds() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let defaults = GENES.iter().map(|gene| gene.default).collect::<Genome>();
+
+    for gene in GENES {
+      assert!(gene.min <= gene.default && gene.default <= gene.max, "{}", gene.name);
+    }
+
+    for _ in 0 .. 100 {
+      let child = offspring(&mut rng, &defaults, &defaults);
+
+      for (gene, &value) in GENES.iter().zip(&child) {
+        assert!(gene.min <= value && value <= gene.max, "{}", gene.name);
+      }
+    }
+  }
+}
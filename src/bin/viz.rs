@@ -0,0 +1,233 @@
+//! Turn a replay recorded by the bot into a self-contained HTML page.
+//!
+//! ```text
+//! viz <replay> [<html>]
+//! ```
+//!
+//! Replays are written by the bot when `UTG_REPLAY` is set, e.g. with
+//! `arena "UTG_REPLAY=game.replay target/release/codingame-unleash-the-geek" target/release/v04`,
+//! or picked out of a stderr dump of an online match if `REPLAY_DUMP` is set. The page is written
+//! next to the replay unless told otherwise.
+//!
+//! Every turn shows the map as the bot saw it: the known ore, the holes, the radars with their
+//! coverage, the traps, the cells the bot thought dangerous as a heatmap, the robots with their
+//! trails, and the actions, comments and events of the bot. The turn is picked with the slider or
+//! the arrow keys.
+
+use codingame_unleash_the_geek::referee::{Item, RADAR_RANGE};
+use codingame_unleash_the_geek::replay::{Replay, Turn};
+use codingame_unleash_the_geek::view::EntityKind;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+/// Page, in which the turns are inserted in place of `/*TURNS*/`.
+const TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Unleash the Geek replay</title>
+<style>
+  body { font-family: monospace; background: #222; color: #ddd; display: flex; gap: 1em; }
+  svg { background: #5a4632; }
+  #side { min-width: 32em; }
+  #side div { white-space: pre; }
+  .events { color: #999; }
+</style>
+</head>
+<body>
+<div>
+  <svg id="map"></svg>
+  <div>
+    <input id="slider" type="range" min="0" value="0" style="width: 100%">
+    <label><input id="trails" type="checkbox" checked> trails</label>
+    <label><input id="coverage" type="checkbox" checked> radar coverage</label>
+    <label><input id="danger" type="checkbox" checked> danger</label>
+  </div>
+</div>
+<div id="side"></div>
+<script>
+const W = /*WIDTH*/, H = /*HEIGHT*/, RANGE = /*RANGE*/, C = 28, TRAIL = 8;
+const TURNS = /*TURNS*/;
+const NS = "http://www.w3.org/2000/svg";
+const svg = document.getElementById("map");
+const slider = document.getElementById("slider");
+const side = document.getElementById("side");
+svg.setAttribute("width", W * C);
+svg.setAttribute("height", H * C);
+slider.max = TURNS.length - 1;
+
+function el(name, attrs, parent, text) {
+  const e = document.createElementNS(NS, name);
+  for (const k in attrs) e.setAttribute(k, attrs[k]);
+  if (text !== undefined) e.textContent = text;
+  parent.appendChild(e);
+  return e;
+}
+
+function center(v) { return v * C + C / 2; }
+
+function draw(t) {
+  const turn = TURNS[t];
+  svg.innerHTML = "";
+
+  for (let y = 0; y < H; y++) {
+    for (let x = 0; x < W; x++) {
+      const i = y * W + x, ore = turn.ore[i];
+      const fill = x == 0 ? "#333" : ore < 0 ? "#6b5640" : ore == 0 ? "#8a7355" : "hsl(45, 90%, " + (70 - 8 * Math.min(ore, 5)) + "%)";
+      el("rect", { x: x * C, y: y * C, width: C - 1, height: C - 1, fill: fill }, svg);
+      if (ore > 0) el("text", { x: x * C + 2, y: y * C + 10, "font-size": 9, fill: "#000" }, svg, ore);
+      if (turn.holes[i] == "1") el("circle", { cx: center(x), cy: center(y), r: C / 5, fill: "#2a1e12" }, svg);
+    }
+  }
+
+  if (document.getElementById("danger").checked) {
+    const dangerous = new Set(turn.dangerous.map(([x, y]) => x + "," + y));
+    for (let y = 0; y < H; y++) {
+      for (let x = 0; x < W; x++) {
+        const heat = [[0, 0], [-1, 0], [1, 0], [0, -1], [0, 1]].filter(([dx, dy]) => dangerous.has((x + dx) + "," + (y + dy))).length;
+        if (heat > 0) el("rect", { x: x * C, y: y * C, width: C - 1, height: C - 1, fill: "red", "fill-opacity": 0.15 * heat }, svg);
+      }
+    }
+  }
+
+  for (const [id, kind, x, y] of turn.entities) {
+    if (kind == 2 && document.getElementById("coverage").checked) {
+      const r = (RANGE + 0.5) * C, cx = center(x), cy = center(y);
+      el("polygon", { points: [[cx, cy - r], [cx + r, cy], [cx, cy + r], [cx - r, cy]].join(" "), fill: "#4af", "fill-opacity": 0.12, stroke: "#4af" }, svg);
+    }
+  }
+
+  if (document.getElementById("trails").checked) {
+    for (const [id, kind] of turn.entities.filter(([, kind]) => kind < 2)) {
+      const points = [];
+      for (let p = Math.max(0, t - TRAIL); p <= t; p++) {
+        const robot = TURNS[p].entities.find(([other]) => other == id);
+        if (robot && robot[2] >= 0) points.push([center(robot[2]), center(robot[3])]);
+      }
+      el("polyline", { points: points.join(" "), fill: "none", stroke: kind == 0 ? "#4af" : "#f84", "stroke-opacity": 0.6, "stroke-width": 2 }, svg);
+    }
+  }
+
+  let index = 0;
+  for (const [id, kind, x, y, item] of turn.entities) {
+    if (kind == 2) {
+      el("text", { x: x * C + C - 10, y: y * C + C - 3, "font-size": 11, fill: "#4af" }, svg, "R");
+    } else if (kind == 3) {
+      el("text", { x: x * C + C - 10, y: y * C + C - 3, "font-size": 11, fill: "red" }, svg, "T");
+    } else if (x >= 0) {
+      el("circle", { cx: center(x), cy: center(y), r: C / 3, fill: kind == 0 ? "#4af" : "#f84", stroke: "#000" }, svg);
+      el("text", { x: center(x) - 4, y: center(y) + 4, "font-size": 11, fill: "#000" }, svg, kind == 0 ? index : (item || ""));
+      if (kind == 0 && item) el("text", { x: center(x) + 5, y: center(y) - 5, "font-size": 9, fill: "#fff" }, svg, item);
+    }
+    if (kind == 0) index++;
+  }
+
+  side.innerHTML = "";
+  const line = (text, cls) => { const d = document.createElement("div"); d.textContent = text; if (cls) d.className = cls; side.appendChild(d); };
+  line("turn " + t + "   score " + turn.scores[0] + " - " + turn.scores[1]);
+  line("");
+  turn.outputs.forEach((output, i) => line(i + ": " + output));
+  line("");
+  turn.events.forEach(event => line(event, "events"));
+}
+
+slider.oninput = () => draw(+slider.value);
+document.querySelectorAll("input[type=checkbox]").forEach(box => box.onchange = () => draw(+slider.value));
+document.onkeydown = e => {
+  if (e.key == "ArrowRight") slider.value = Math.min(+slider.value + 1, TURNS.length - 1);
+  else if (e.key == "ArrowLeft") slider.value = Math.max(+slider.value - 1, 0);
+  else return;
+  draw(+slider.value);
+};
+draw(0);
+</script>
+</body>
+</html>
+"##;
+
+/// A string as a JavaScript literal.
+fn js_string(s: &str) -> String {
+  let mut literal = String::from("\"");
+
+  for c in s.chars() {
+    match c {
+      '"' => literal += "\\\"",
+      '\\' => literal += "\\\\",
+      '<' => literal += "\\u003c",
+      c if (c as u32) < 0x20 => literal += &format!("\\u{:04x}", c as u32),
+      c => literal.push(c),
+    }
+  }
+
+  literal.push('"');
+  literal
+}
+
+fn js_strings(strings: &[String]) -> String {
+  format!("[{}]", strings.iter().map(|s| js_string(s)).collect::<Vec<_>>().join(","))
+}
+
+/// A turn as a JavaScript object.
+fn js_turn(turn: &Turn) -> String {
+  let view = &turn.view;
+  let ore = view.ore.iter().map(|ore| ore.map(|ore| ore as i64).unwrap_or(-1).to_string()).collect::<Vec<_>>();
+  let holes = view.holes.iter().map(|&hole| if hole { '1' } else { '0' }).collect::<String>();
+  let entities = view.entities.iter().map(|entity| {
+    let kind = match entity.kind {
+      EntityKind::Robot => 0,
+      EntityKind::OpponentRobot => 1,
+      EntityKind::Radar => 2,
+      EntityKind::Trap => 3,
+    };
+    let item = match entity.item {
+      Some(Item::Radar) => "\"R\"",
+      Some(Item::Trap) => "\"T\"",
+      Some(Item::Ore) => "\"O\"",
+      None => "null",
+    };
+
+    format!("[{},{},{},{},{}]", entity.id, kind, entity.x, entity.y, item)
+  }).collect::<Vec<_>>();
+  let dangerous = turn.dangerous.iter().map(|[x, y]| format!("[{},{}]", x, y)).collect::<Vec<_>>();
+
+  format!(
+    "{{scores:[{},{}],ore:[{}],holes:\"{}\",entities:[{}],dangerous:[{}],outputs:{},events:{}}}",
+    view.scores[0], view.scores[1], ore.join(","), holes, entities.join(","), dangerous.join(","),
+    js_strings(&turn.outputs), js_strings(&turn.events)
+  )
+}
+
+fn page(replay: &Replay) -> String {
+  let turns = replay.turns.iter().map(js_turn).collect::<Vec<_>>().join(",\n");
+
+  TEMPLATE
+    .replace("/*WIDTH*/", &replay.width.to_string())
+    .replace("/*HEIGHT*/", &replay.height.to_string())
+    .replace("/*RANGE*/", &RADAR_RANGE.to_string())
+    .replace("/*TURNS*/", &format!("[\n{}\n]", turns))
+}
+
+fn run() -> Result<(), String> {
+  let args = std::env::args().skip(1).collect::<Vec<_>>();
+  let input = args.first().ok_or("usage: viz <replay> [<html>]")?;
+  let output = args.get(1).cloned().unwrap_or_else(|| Path::new(input).with_extension("html").to_string_lossy().into_owned());
+
+  let replay = Replay::load(input).map_err(|err| format!("{}: {}", input, err))?;
+
+  if replay.turns.is_empty() {
+    return Err(format!("{}: no turn recorded", input));
+  }
+
+  fs::write(&output, page(&replay)).map_err(|err| format!("{}: {}", output, err))?;
+  println!("{} turn(s) written to {}", replay.turns.len(), output);
+
+  Ok(())
+}
+
+fn main() {
+  if let Err(err) = run() {
+    eprintln!("{}", err);
+    exit(1);
+  }
+}
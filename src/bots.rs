@@ -0,0 +1,121 @@
+//! Building blocks of the reference bots in `src/bin`, used as opponents in offline matches.
+
+use std::io::{self, Write};
+
+use crate::referee::{manh_dist, Action, Item};
+use crate::view::{read_init, Entity, EntityKind, PlayerView};
+
+/// Play a whole game on stdin/stdout, printing the actions decided every turn.
+pub fn run<F>(mut decide: F) where F: FnMut(&PlayerView) -> Vec<Action> {
+  let stdin = io::stdin();
+  let mut input = stdin.lock();
+  let (width, height) = read_init(&mut input).expect("cannot read the map size");
+  let stdout = io::stdout();
+
+  while let Some(view) = PlayerView::read(width, height, &mut input).expect("cannot read the turn input") {
+    let mut output = stdout.lock();
+
+    for action in decide(&view) {
+      let _ = writeln!(output, "{}", action);
+    }
+
+    let _ = output.flush();
+  }
+}
+
+/// Cells of the map, HQ excluded, closest to a given cell first.
+pub fn cells_around(view: &PlayerView, from: [i32; 2]) -> Vec<[i32; 2]> {
+  let mut cells = (1 .. view.width as i32)
+    .flat_map(|x| (0 .. view.height as i32).map(move |y| [x, y]))
+    .collect::<Vec<_>>();
+
+  cells.sort_by_key(|&cell| manh_dist(from, cell));
+  cells
+}
+
+/// Closest cell with some known ore nobody claimed yet.
+pub fn nearest_ore(view: &PlayerView, from: [i32; 2], claimed: &[[i32; 2]]) -> Option<[i32; 2]> {
+  cells_around(view, from).into_iter().find(|&[x, y]| {
+    view.ore_at(x, y).unwrap_or(0) > 0 && !claimed.contains(&[x, y])
+  })
+}
+
+/// Closest cell with neither known ore nor a hole nobody claimed yet.
+pub fn nearest_unknown(view: &PlayerView, from: [i32; 2], claimed: &[[i32; 2]]) -> Option<[i32; 2]> {
+  cells_around(view, from).into_iter().find(|&[x, y]| {
+    x > 1 && view.ore_at(x, y).is_none() && !view.hole_at(x, y) && !claimed.contains(&[x, y])
+  })
+}
+
+/// Whether the robot is the first one of its team in HQ able to request an item this turn.
+///
+/// Only the robots wanting the item compete, `wants` being given the index of every robot of the
+/// team and the robot itself; the others don’t hold the item back while idling in HQ.
+pub fn first_requester<F>(view: &PlayerView, robot: &Entity, item: Item, wants: F) -> bool
+where F: Fn(usize, &Entity) -> bool {
+  let cooldown = match item {
+    Item::Radar => view.radar_cooldown,
+    _ => view.trap_cooldown,
+  };
+
+  cooldown == 0 && view.robots()
+    .enumerate()
+    .find(|&(index, other)| !other.is_dead() && other.x == 0 && other.item.is_none() && wants(index, other))
+    .map(|(_, other)| other.id == robot.id)
+    .unwrap_or(false)
+}
+
+/// Nearest-ore greedy mining: deliver ore, dig the closest known ore, or else the closest unknown
+/// cell. The dug cell is claimed so that the other robots go elsewhere.
+pub fn greedy(view: &PlayerView, robot: &Entity, claimed: &mut Vec<[i32; 2]>) -> Action {
+  if robot.is_dead() {
+    return Action::Wait;
+  }
+
+  if robot.item == Some(Item::Ore) {
+    return Action::Move(0, robot.y);
+  }
+
+  let from = [robot.x, robot.y];
+
+  match nearest_ore(view, from, claimed).or_else(|| nearest_unknown(view, from, claimed)) {
+    Some([x, y]) => {
+      claimed.push([x, y]);
+      Action::Dig(x, y)
+    }
+
+    None => Action::Wait
+  }
+}
+
+/// Whether an entity is one of the opponent robots still alive.
+pub fn is_live_opponent(entity: &Entity) -> bool {
+  entity.kind == EntityKind::OpponentRobot && !entity.is_dead()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::referee::Game;
+
+  #[test]
+  fn idle_robots_dont_hold_requests_back() {
+    let mut game = Game::generate(0);
+
+    for robot in game.robots.iter_mut() {
+      robot.x = 0;
+    }
+
+    let view = PlayerView::fog(&game, 0);
+    let robots = view.robots().collect::<Vec<_>>();
+
+    assert!(first_requester(&view, robots[0], Item::Trap, |_, _| true));
+    assert!(!first_requester(&view, robots[1], Item::Trap, |_, _| true));
+    assert!(!first_requester(&view, robots[0], Item::Trap, |index, _| index != 0));
+    assert!(first_requester(&view, robots[1], Item::Trap, |index, _| index != 0));
+
+    game.trap_cooldowns[0] = 1;
+    let view = PlayerView::fog(&game, 0);
+    assert!(!first_requester(&view, view.robots().nth(1).unwrap(), Item::Trap, |index, _| index != 0));
+  }
+}
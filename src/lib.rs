@@ -0,0 +1,12 @@
+//! Offline tooling: a rules engine playing Unleash the Geek matches between bot executables.
+//!
+//! The bot itself lives in `main.rs` and doesn’t depend on any of this, since CodinGame only
+//! accepts a single source file.
+
+pub mod arena;
+pub mod bots;
+pub mod map;
+pub mod player;
+pub mod referee;
+pub mod replay;
+pub mod view;
@@ -1,8 +1,9 @@
 use rand::{Rng, thread_rng};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::collections::btree_map::Entry;
 use std::fmt;
-use std::io;
+use std::io::{self, BufRead, Write};
 
 /// Threshold of amount of ore we want to have under radars. If we exceed that value, we stop
 /// burrying radars.
@@ -28,10 +29,257 @@ const EXPLORATION_START_MIN_DISTANCE: i32 = 3;
 ///   exploration_distance_max = exploration_distance + EXPLORATION_DELTA_DISTANCE
 const EXPLORATION_DELTA_DISTANCE: i32 = 5;
 
+/// Maximum number of radars we burry.
+const MAX_RADARS: usize = 10;
+
+/// Number of cells a miner can travel in a single turn.
+const MINER_SPEED: i32 = 4;
+
+/// Cooldown the referee applies to an item once it has been requested.
+const ITEM_COOLDOWN: u32 = 5;
+
+/// Number of turns ahead the logistics scheduler plans item pickups for.
+const LOGISTICS_HORIZON: u32 = 10;
+
+/// Maximum number of turns a carrier is allowed to wait at HQ for its item to be available.
+const MAX_PICKUP_WAIT: u32 = 1;
+
+/// Should we “flash” radars — i.e. burry them, read the revealed ore and dig them back out to
+/// redeploy them elsewhere? The official referee doesn’t give radars back, in which case flashed
+/// radars stay where they were first burried.
+const RADAR_FLASHING: bool = false;
+
+/// Maximum number of miners flashing radars at the same time.
+const MAX_FLASHING_CARRIERS: usize = 1;
+
+/// Minimum number of never-observed cells a flashing spot must reveal to be worth it.
+const FLASH_MIN_UNKNOWN_CELLS: i32 = 10;
+
+/// Number of turns after which an ore value observed by a radar is not trusted anymore.
+const RADAR_ORE_STALENESS: u32 = 40;
+
+/// Number of turns after which an inferred ore value is not trusted anymore.
+const INFERRED_ORE_STALENESS: u32 = 20;
+
+/// Minimum expected amount of ore a cell must have for us to send a miner there.
+const MIN_EXPECTED_ORE: f32 = 0.5;
+
+/// Number of turns in a game.
+const MAX_TURNS: u32 = 200;
+
+/// Number of turns before the end of the game at which we switch to the endgame mode.
+const ENDGAME_TURNS: u32 = 20;
+
+/// Number of remaining turns that make a point of score delta matter.
+///
+/// The score margin required to consider we’re leading or trailing is computed as:
+///
+///   margin = 1 + remaining_turns / SCORE_MARGIN_TURNS
+const SCORE_MARGIN_TURNS: u32 = 20;
+
+/// Amount of known ore under which we consider the ore is scarce.
+const SCARCE_ORE_AMOUNT: usize = 15;
+
+/// Minimum amount of ore a cell must have to be worth trapping.
+const MIN_TRAP_ORE: usize = 2;
+
+/// Maximum number of traps we burry.
+const MAX_TRAPS: usize = 5;
+
+/// Number of turns we remember the opponents’ stops for.
+const CONTESTED_WINDOW: u32 = 20;
+
+/// Minimum number of opponents’ stops at the same place for a vein to be considered contested.
+const CONTESTED_MIN_STOPS: usize = 2;
+
+/// Distance bonus given to the cells of a vein we are racing the opponent for.
+const RACE_DISTANCE_BONUS: i32 = 4;
+
+/// Initial odds that an opponent burried a radar rather than a trap.
+const RADAR_PRIOR_ODDS: f32 = 1.;
+
+/// Factor applied to the radar odds of a burial every time an opponent digs a cell we know has ore
+/// around it.
+const RADAR_EVIDENCE_FACTOR: f32 = 2.;
+
+/// Probability above which we consider a burial is a radar.
+const RADAR_LIKELY: f32 = 0.6;
+
+/// Factor applied to the radar odds of a burial made in a place we have no ore information about.
+///
+/// Opponents going straight to unknown territory are likely to be deploying radars, while traps
+/// are burried on the ore they already know about.
+const UNKNOWN_BURIAL_RADAR_FACTOR: f32 = 3.;
+
+/// Factor applied to the radar odds of a burial made right next to some ore.
+const ORE_BURIAL_RADAR_FACTOR: f32 = 0.25;
+
+/// Amount of ore we assume an unknown cell has when estimating what a radar reveals.
+const UNKNOWN_CELL_ORE_GUESS: f32 = 0.3;
+
+/// Average number of turns a miner needs to bring a unit of ore back to HQ.
+const ORE_ROUND_TRIP_TURNS: f32 = 8.;
+
+/// Maximum number of turns a miner travels to destroy a suspected burial.
+const BURIAL_DESTROY_MAX_TURNS: u32 = 3;
+
+/// Maximum number of turns we wait for our other miners to move away from a burial we want to
+/// destroy before giving it up.
+const BURIAL_DESTROY_MAX_WAITS: u32 = 3;
+
+/// Number of extra turns we accept to travel in order not to end a turn next to a suspected trap.
+const DANGER_EXPOSURE_TURNS: u32 = 2;
+
+/// Dump the map as we see it on stderr every that many turns, 0 disabling the dump; dumping every
+/// turn would flood the CodinGame console.
+const DEBUG_GRID_EVERY: u32 = 10;
+
+/// Maximum length of the comments shown by the miners in the viewer; 0 disables comments.
+const MAX_COMMENT_LENGTH: usize = 40;
+
+/// Dump a replay of the game (see Replay) on stderr, e.g. to import online matches.
+const REPLAY_DUMP: bool = false;
+
+/// Events printed on stderr every turn: none (0), anomalies (1), decisions (2) or inferences too
+/// (3); see Level. More than anomalies floods the CodinGame console (raise it locally with
+/// UTG_VERBOSITY).
+const VERBOSITY: u32 = 1;
+
 macro_rules! parse_input {
     ($x:expr, $t:ident) => ($x.trim().parse::<$t>().unwrap())
 }
 
+/// Declare the tunable parameters along with their default values.
+macro_rules! params {
+  ($($(#[$doc:meta])* $field:ident: $t:ty = $default:expr,)*) => {
+    /// Tunable parameters.
+    ///
+    /// The defaults are the constants above, which is what is played on CodinGame; with the `local`
+    /// feature, they can be overridden for local runs (see Params::load).
+    #[derive(Clone, Debug, PartialEq)]
+    struct Params {
+      $($(#[$doc])* $field: $t,)*
+    }
+
+    impl Default for Params {
+      fn default() -> Self {
+        Params {
+          $($field: $default,)*
+        }
+      }
+    }
+
+    impl Params {
+      /// Names of the parameters.
+      #[cfg(feature = "local")]
+      const NAMES: &'static [&'static str] = &[$(stringify!($field)),*];
+
+      /// Set a parameter from its textual value.
+      #[cfg(feature = "local")]
+      fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+          $(stringify!($field) => {
+            self.$field = value.trim().parse().map_err(|e| format!("invalid {} value {}: {}", key, value.trim(), e))?;
+          })*
+
+          _ => return Err(format!("unknown parameter {}", key))
+        }
+
+        Ok(())
+      }
+    }
+
+    impl fmt::Display for Params {
+      fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        $(writeln!(f, "{} = {}", stringify!($field), self.$field)?;)*
+        Ok(())
+      }
+    }
+  }
+}
+
+params! {
+  /// See MIN_ORE_NO_RADAR_NEEDED.
+  min_ore_no_radar_needed: usize = MIN_ORE_NO_RADAR_NEEDED,
+  /// See MAX_RANDOM_SAFE_DEST_TRIES.
+  max_random_safe_dest_tries: usize = MAX_RANDOM_SAFE_DEST_TRIES,
+  /// See EXPLORATION_RATIO_THRESHOLD.
+  exploration_ratio_threshold: f32 = EXPLORATION_RATIO_THRESHOLD,
+  /// See EXPLORATION_START_MIN_DISTANCE.
+  exploration_start_min_distance: i32 = EXPLORATION_START_MIN_DISTANCE,
+  /// See EXPLORATION_DELTA_DISTANCE.
+  exploration_delta_distance: i32 = EXPLORATION_DELTA_DISTANCE,
+  /// See MAX_RADARS.
+  max_radars: usize = MAX_RADARS,
+  /// See SCARCE_ORE_AMOUNT.
+  scarce_ore_amount: usize = SCARCE_ORE_AMOUNT,
+  /// See MAX_TRAPS.
+  max_traps: usize = MAX_TRAPS,
+  /// See RACE_DISTANCE_BONUS.
+  race_distance_bonus: i32 = RACE_DISTANCE_BONUS,
+  /// See RADAR_LIKELY.
+  radar_likely: f32 = RADAR_LIKELY,
+  /// See DANGER_EXPOSURE_TURNS.
+  danger_exposure_turns: u32 = DANGER_EXPOSURE_TURNS,
+  /// See DEBUG_GRID_EVERY.
+  debug_grid_every: u32 = DEBUG_GRID_EVERY,
+  /// See MAX_COMMENT_LENGTH.
+  max_comment_length: usize = MAX_COMMENT_LENGTH,
+  /// See VERBOSITY.
+  verbosity: u32 = VERBOSITY,
+}
+
+/// Environment variable holding the path of a parameter file.
+#[cfg(feature = "local")]
+const PARAMS_FILE_VAR: &str = "UTG_PARAMS";
+
+impl Params {
+  /// Parameters to play with.
+  ///
+  /// With the `local` feature, the defaults are overridden by the `key = value` lines of the file
+  /// UTG_PARAMS points to (so simple TOML files work too), and then by the `UTG_<KEY>` environment
+  /// variables, e.g. `UTG_MAX_RADARS=8`.
+  #[cfg(feature = "local")]
+  fn load() -> Self {
+    let mut params = Params::default();
+
+    if let Ok(path) = std::env::var(PARAMS_FILE_VAR) {
+      match std::fs::read_to_string(&path) {
+        Ok(content) => {
+          for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let result = match line.find('=') {
+              Some(i) => params.set(line[.. i].trim(), &line[i + 1 ..]),
+              None => Err(format!("missing value in {}", line)),
+            };
+
+            if let Err(err) = result {
+              eprintln!("{}: {}", path, err);
+            }
+          }
+        }
+
+        Err(err) => eprintln!("cannot read parameters from {}: {}", path, err)
+      }
+    }
+
+    for name in Params::NAMES {
+      if let Ok(value) = std::env::var(format!("UTG_{}", name.to_uppercase())) {
+        if let Err(err) = params.set(name, &value) {
+          eprintln!("{}", err);
+        }
+      }
+    }
+
+    params
+  }
+
+  /// Parameters to play with.
+  #[cfg(not(feature = "local"))]
+  fn load() -> Self {
+    Params::default()
+  }
+}
+
 /// Compute the “Manhattan distance” between two points.
 fn manh_dist(a: [i32; 2], b: [i32; 2]) -> i32 {
   (a[0] - b[0]).abs() + (a[1] - b[1]).abs()
@@ -43,6 +291,44 @@ fn is_cell_at_range(a: [i32; 2], b: [i32; 2]) -> bool {
    (a[0] == b[0] || a[1] == b[1]) && manh_dist(a, b) <= 1
 }
 
+/// Number of turns required to travel from a cell to another one.
+fn turns_to_reach(a: [i32; 2], b: [i32; 2]) -> u32 {
+  ((manh_dist(a, b) + MINER_SPEED - 1) / MINER_SPEED) as u32
+}
+
+/// Number of turns required to go dig a cell from a given position and bring the ore back to HQ.
+fn delivery_turns(from: [i32; 2], dig: [i32; 2]) -> u32 {
+  // we dig from a neighbour, so the ore cell itself doesn’t need to be reached
+  let dig_from_dist = (manh_dist(from, dig) - 1).max(0);
+  let travel = ((dig_from_dist + MINER_SPEED - 1) / MINER_SPEED) as u32;
+  let back = turns_to_reach([(dig[0] - 1).max(0), dig[1]], [0, dig[1]]);
+
+  travel + 1 + back
+}
+
+/// Position a miner ends its turn at when executing a request from a given cell.
+///
+/// The referee doesn’t tell which path it takes, so we assume horizontal moves come first.
+fn end_position(from: [i32; 2], req: Request) -> [i32; 2] {
+  match req {
+    Request::Move(x, y) => {
+      let mut position = from;
+
+      for _ in 0 .. MINER_SPEED {
+        if position[0] != x {
+          position[0] += (x - position[0]).signum();
+        } else if position[1] != y {
+          position[1] += (y - position[1]).signum();
+        }
+      }
+
+      position
+    }
+
+    _ => from
+  }
+}
+
 trait TryFrom<T>: Sized {
   type Error;
 
@@ -132,6 +418,22 @@ impl fmt::Display for RequestItem {
   }
 }
 
+impl From<RequestItem> for Item {
+  fn from(item: RequestItem) -> Self {
+    match item {
+      RequestItem::Radar => Item::Radar,
+      RequestItem::Trap => Item::Trap,
+    }
+  }
+}
+
+/// A planned item pickup.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct ItemPickup {
+  item: RequestItem,
+  turn: u32, // number of turns from now when the item will be available
+}
+
 /// Possible request.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum Request {
@@ -153,10 +455,6 @@ impl fmt::Display for Request {
 }
 
 impl Request {
-  fn submit(self) {
-    println!("{}", self);
-  }
-
   fn comment<S>(self, msg: S) -> RequestComment where S: Into<String> {
     RequestComment::new(self, Some(msg.into()))
   }
@@ -184,6 +482,22 @@ impl RequestComment {
   fn submit(self) {
     println!("{}", self);
   }
+
+  /// Prefix the comment with a tag.
+  fn tagged<S>(mut self, tag: S) -> Self where S: AsRef<str> {
+    self.comment = Some(match self.comment {
+      Some(comment) => format!("{} {}", tag.as_ref(), comment),
+      None => tag.as_ref().to_owned()
+    });
+
+    self
+  }
+
+  /// Cut the comment down to a maximum length, dropping it altogether if that length is 0.
+  fn limited(mut self, max_length: usize) -> Self {
+    self.comment = self.comment.filter(|_| max_length > 0).map(|comment| comment.chars().take(max_length).collect());
+    self
+  }
 }
 
 impl From<Request> for RequestComment {
@@ -204,53 +518,299 @@ impl fmt::Display for RequestComment {
   }
 }
 
+/// Strategic mode, switched according to the score and the remaining ore.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Mode {
+  /// Scores are tied; just mine as usual.
+  Balanced,
+  /// We’re leading; mine while taking as few risks as possible.
+  SafeMining,
+  /// We’re trailing; trap the ore veins and blow our traps up when it kills more opponents than
+  /// our own miners.
+  Aggressive,
+  /// The remaining ore is scarce; prevent the opponent from getting it: trap like in the
+  /// aggressive mode, and blow our traps up even when it kills as many of our miners.
+  Denial,
+}
+
+/// Role of one of our miners, rebalanced every turn.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Role {
+  /// Digs ore and brings it back to HQ.
+  Prospector,
+  /// Picks radars up at HQ and burries them.
+  RadarRunner,
+  /// Picks traps up at HQ and burries them.
+  Trapper,
+  /// Destroys the opponent burials.
+  Guard,
+}
+
+impl Role {
+  /// Role of the miners carrying a given item.
+  fn carrying(item: RequestItem) -> Self {
+    match item {
+      RequestItem::Radar => Role::RadarRunner,
+      RequestItem::Trap => Role::Trapper,
+    }
+  }
+
+  /// Single letter standing for this role, used in the miner comments.
+  fn symbol(self) -> char {
+    match self {
+      Role::Prospector => 'P',
+      Role::RadarRunner => 'R',
+      Role::Trapper => 'T',
+      Role::Guard => 'G',
+    }
+  }
+}
+
+impl fmt::Display for Role {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      Role::Prospector => f.write_str("prospector"),
+      Role::RadarRunner => f.write_str("radar runner"),
+      Role::Trapper => f.write_str("trapper"),
+      Role::Guard => f.write_str("guard"),
+    }
+  }
+}
+
+/// What we do about a vein an opponent is mining.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum VeinDecision {
+  /// We can get some ore before they exhaust it, so we send our miners there.
+  Race,
+  /// They will exhaust it before we get there; trap it.
+  Trap,
+}
+
+impl fmt::Display for VeinDecision {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      VeinDecision::Race => f.write_str("race"),
+      VeinDecision::Trap => f.write_str("trap"),
+    }
+  }
+}
+
+/// A place where we think an opponent burried an item.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BurialSuspect {
+  cell: [i32; 2], // burial cell, or the opponent position if we don’t know exactly
+  exact: bool, // whether we saw the hole appear, so that cell is exactly where the item is
+  turn: u32,
+  radar_odds: f32, // odds that the item is a radar rather than a trap
+  waits: u32, // turns we waited for our miners to move away before destroying it
+}
+
+impl BurialSuspect {
+  /// Probability that the burried item is a radar.
+  fn radar_likelihood(&self) -> f32 {
+    self.radar_odds / (1. + self.radar_odds)
+  }
+}
+
+/// Importance of an event, the verbosity it gets printed from.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Level {
+  /// Something that shouldn’t happen.
+  Anomaly = 1,
+  /// Something we decided to do.
+  Decision = 2,
+  /// Something we deduced from what we saw.
+  Inference = 3,
+}
+
+/// Something that happened during a turn.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Event {
+  turn: u32,
+  uid: Option<UID>, // entity concerned, usually a miner
+  kind: EventKind,
+}
+
+/// What happened, along with what we know about it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum EventKind {
+  /// An entity was given an update for a kind of entity it isn’t.
+  Mismatch { update: &'static str, expected: &'static str },
+  /// A miner will pick an item up.
+  PickupScheduled { item: RequestItem, turns: u32 },
+  /// A miner switched roles.
+  RoleChanged(Role),
+  /// The exploration window moved away, from a distance to HQ to another.
+  ExplorationWindow { from: i32, to: i32 },
+  /// The strategic mode switched.
+  ModeSwitched { from: Mode, to: Mode, delta: i64, margin: i64 },
+  /// A miner goes destroy a suspected burial.
+  Destroying([i32; 2]),
+  /// A vein opponents are mining got a new decision.
+  VeinContested { stop: [i32; 2], stops: usize, ore_left: Option<usize>, decision: VeinDecision },
+  /// A miner moves elsewhere so as not to share a trap with another miner.
+  Spreading { from: [i32; 2], to: [i32; 2] },
+  /// One of our miners died.
+  Died { item: Option<Item>, order: Order },
+  /// An opponent stopped in HQ.
+  RequestSuspected,
+  /// An opponent stopped after an item request.
+  BurialSuspected([i32; 2]),
+  /// A cell might hold a trap.
+  Dangerous([i32; 2]),
+  /// A dangerous cell turned out not to hold any trap.
+  Safe([i32; 2]),
+}
+
+impl EventKind {
+  fn level(&self) -> Level {
+    match *self {
+      EventKind::Mismatch { .. } | EventKind::Died { .. } => Level::Anomaly,
+
+      EventKind::PickupScheduled { .. }
+      | EventKind::RoleChanged(..)
+      | EventKind::ExplorationWindow { .. }
+      | EventKind::ModeSwitched { .. }
+      | EventKind::Destroying(..)
+      | EventKind::VeinContested { .. }
+      | EventKind::Spreading { .. } => Level::Decision,
+
+      EventKind::RequestSuspected
+      | EventKind::BurialSuspected(..)
+      | EventKind::Dangerous(..)
+      | EventKind::Safe(..) => Level::Inference,
+    }
+  }
+}
+
+impl fmt::Display for EventKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      EventKind::Mismatch { update, expected } => write!(f, "{} update, but not a {}", update, expected),
+      EventKind::PickupScheduled { item, turns } => write!(f, "picks a {} in {} turn(s)", item, turns),
+      EventKind::RoleChanged(role) => write!(f, "now a {}", role),
+      EventKind::ExplorationWindow { from, to } => write!(f, "exploration window {} -> {}", from, to),
+      EventKind::ModeSwitched { from, to, delta, margin } => write!(f, "mode {} -> {} (delta {}, margin {})", from, to, delta, margin),
+      EventKind::Destroying([x, y]) => write!(f, "destroys ({}, {})", x, y),
+      EventKind::VeinContested { stop: [x, y], stops, ore_left, decision } => {
+        write!(f, "vein ({}, {}) contested: {} ({} stops, {:?} ore left)", x, y, decision, stops, ore_left)
+      }
+      EventKind::Spreading { from: [fx, fy], to: [x, y] } => write!(f, "spreads to ({}, {}) instead of ({}, {})", x, y, fx, fy),
+      EventKind::Died { item, order } => write!(f, "died carrying {:?} with order {:?}", item, order),
+      EventKind::RequestSuspected => f.write_str("might be asking for an item"),
+      EventKind::BurialSuspected([x, y]) => write!(f, "might be burying an item around ({}, {})", x, y),
+      EventKind::Dangerous([x, y]) => write!(f, "({}, {}) is dangerous", x, y),
+      EventKind::Safe([x, y]) => write!(f, "({}, {}) is actually safe", x, y),
+    }
+  }
+}
+
+impl fmt::Display for Event {
+  /// Compact form: turn, uid (`-` if none) and what happened.
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self.uid {
+      Some(uid) => write!(f, "{:3} #{:<2} {}", self.turn, uid, self.kind),
+      None => write!(f, "{:3}  -  {}", self.turn, self.kind),
+    }
+  }
+}
+
+impl Mode {
+  /// Whether we burry traps in that mode.
+  fn uses_traps(self) -> bool {
+    matches!(self, Mode::Aggressive | Mode::Denial)
+  }
+}
+
+impl fmt::Display for Mode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      Mode::Balanced => f.write_str("even"),
+      Mode::SafeMining => f.write_str("safe"),
+      Mode::Aggressive => f.write_str("aggro"),
+      Mode::Denial => f.write_str("deny"),
+    }
+  }
+}
+
 /// Unique ID for all entities in the game.
+#[allow(clippy::upper_case_acronyms)]
 type UID = u32;
 
 #[derive(Debug)]
 struct GameState {
   // informational
+  params: Params,
   width: usize,
   height: usize,
   my_score: u32,
   opponent_score: u32,
-  cells: Vec<Cell>,
+  grid: KnowledgeGrid,
   miners: Vec<Miner>,
   opponent_miners: Vec<Miner>,
   entities: HashMap<UID, Entity>,
   burried_radars: HashMap<UID, [i32; 2]>,
   burried_traps: HashMap<UID, [i32; 2]>,
+  trap_cells: HashSet<[i32; 2]>, // cells of burried_traps, to look them up quickly
   radar_cooldown: u32,
   trap_cooldown: u32,
+  turn: u32,
+  seen_items: HashSet<UID>, // burried items seen this turn
+  events: Vec<Event>, // what happened this turn
 
   // tactical
-  miner_with_radar: Option<usize>,
+  mode: Mode,
+  trap_targets: Vec<[i32; 2]>, // cells we want a trap to be burried in
+  pickup_schedule: Vec<ItemPickup>, // item pickups planned for the next turns
   dangerous_opponents: HashSet<usize>,
+  opponent_stops: Vec<([i32; 2], u32)>, // places and turns opponents stopped at to dig
+  contested_veins: HashMap<[i32; 2], VeinDecision>, // veins opponents are mining, by stop place
+  burial_suspects: Vec<BurialSuspect>,
   dangerous_cells: HashMap<[i32; 2], Cell>,
   exploration_distance: i32, // max distance we’ve randomly explored so far
-  cells_snapshot: Vec<Cell>, // a snapshot of self.cells that gets updated only for “better”
 }
 
 impl GameState {
-  fn new(width: usize, height: usize) -> Self {
+  fn new(width: usize, height: usize, params: Params) -> Self {
     GameState {
+      exploration_distance: params.exploration_start_min_distance,
+      params,
       width,
       height,
       my_score: 0,
       opponent_score: 0,
-      cells: vec![Cell::default(); width * height],
+      grid: KnowledgeGrid::new(width, height),
       miners: Vec::new(),
       opponent_miners: Vec::new(),
       entities: HashMap::new(),
       burried_radars: HashMap::new(),
       burried_traps: HashMap::new(),
+      trap_cells: HashSet::new(),
       radar_cooldown: 0,
       trap_cooldown: 0,
-      miner_with_radar: None,
+      turn: 0,
+      seen_items: HashSet::new(),
+      events: Vec::new(),
+      mode: Mode::Balanced,
+      trap_targets: Vec::new(),
+      pickup_schedule: Vec::new(),
       dangerous_opponents: HashSet::new(),
+      opponent_stops: Vec::new(),
+      contested_veins: HashMap::new(),
+      burial_suspects: Vec::new(),
       dangerous_cells: HashMap::new(),
-      exploration_distance: EXPLORATION_START_MIN_DISTANCE,
-      cells_snapshot: vec![Cell::default(); width * height],
+    }
+  }
+
+  /// Record an event of the current turn.
+  fn log(&mut self, uid: Option<UID>, kind: EventKind) {
+    self.events.push(Event { turn: self.turn, uid, kind });
+  }
+
+  /// Print the events of the current turn allowed by the verbosity on stderr.
+  fn print_events(&self) {
+    for event in self.events.iter().filter(|event| event.kind.level() as u32 <= self.params.verbosity) {
+      eprintln!("{}", event);
     }
   }
 
@@ -263,18 +823,62 @@ impl GameState {
   }
 
   fn update_cell(&mut self, x: usize, y: usize, ore_amount: Option<usize>, hole: bool) {
-    let index = y * self.width + x;
+    self.grid.observe_cell(self.turn, x as i32, y as i32, ore_amount, hole);
+  }
 
-    self.cells[index].ore_amount = ore_amount;
-    self.cells[y * self.width + x].has_hole = hole;
+  /// Check what our miners got from the cells they dug on the previous turn.
+  ///
+  /// A miner that came back with ore removed a unit from the cell; a miner that came back empty
+  /// handed tells us the cell has no ore left. Burials count too, as the ore of the cell is
+  /// collected once the item is burried.
+  fn observe_digs(&mut self) {
+    for miner_index in 0 .. self.miners.len() {
+      let miner = &self.miners[miner_index];
+
+      if let Some([x, y]) = miner.dug.or(miner.burried) {
+        let got_ore = miner.item == Some(Item::Ore);
+        self.grid.observe_dig(self.turn, x, y, got_ore);
+      }
+    }
+  }
 
-    // for the snapshot, we update only if we have a new value
-    if ore_amount.is_some() {
-      self.cells_snapshot[index].ore_amount = ore_amount;
-      self.cells[y * self.width + x].has_hole = hole;
+  /// Infer ore taken by opponents from the holes that appeared this turn.
+  ///
+  /// A new hole we haven’t dug nor burried anything in, outside of our radars and without any
+  /// opponent carrying an item around, is very likely an opponent who took a unit of ore.
+  fn observe_holes(&mut self) {
+    for [x, y] in self.grid.new_holes().to_vec() {
+      let ours = self.miners().any(|miner| miner.dug == Some([x, y]) || miner.burried == Some([x, y]));
+      let burying = self.dangerous_opponents.iter().any(|&index| {
+        let opponent = &self.opponent_miners[index];
+        is_cell_at_range([opponent.x, opponent.y], [x, y])
+      });
+
+      if !ours && !burying {
+        self.grid.infer_ore_taken(self.turn, x, y);
+      }
     }
   }
 
+  /// Amount of ore we know a cell has left.
+  ///
+  /// If the cell is under one of our radars, this is the live value. Otherwise, it’s the last
+  /// value we have observed, if any, minus what we have removed since.
+  fn known_ore(&self, x: i32, y: i32) -> Option<usize> {
+    self.known_cell(x, y)?.remaining_ore()
+  }
+
+  /// Amount of ore we expect a cell to have left, discounted by the staleness of the information.
+  fn expected_ore(&self, x: i32, y: i32) -> f32 {
+    self.known_cell(x, y).map(|cell| cell.expected_ore(self.turn)).unwrap_or(0.)
+  }
+
+  /// Best information we have about a cell: the live cell if it’s under a radar, the snapshot
+  /// otherwise.
+  fn known_cell(&self, x: i32, y: i32) -> Option<&Cell> {
+    self.grid.known(self.turn, x, y)
+  }
+
   fn set_radar_cooldown(&mut self, cooldown: u32) {
     self.radar_cooldown = cooldown;
   }
@@ -319,7 +923,7 @@ impl GameState {
         miner.y = py;
       }
 
-      _ => eprintln!("trying to update miner {} position, but it’s not a miner", uid)
+      _ => self.log(Some(uid), EventKind::Mismatch { update: "position", expected: "miner" })
     }
   }
 
@@ -333,30 +937,70 @@ impl GameState {
         self.opponent_miners[*index].item = item;
       }
 
-      _ => eprintln!("trying to update miner {} item, but it’s not a miner", uid)
+      _ => self.log(Some(uid), EventKind::Mismatch { update: "item", expected: "miner" })
     }
   }
 
+  /// Mark a miner as dead.
+  ///
+  /// Our dead miners drop their orders so that nothing they were about to do (deploying an item,
+  /// destroying a burial, etc.) is accounted for anymore; the living ones take over on the next
+  /// planning. Dead opponents can’t burry anything anymore.
   fn kill(&mut self, uid: UID) {
     match self.entities.get(&uid) {
-      Some(Entity::Miner(index)) => {
-        self.miners[*index].alive = false;
+      Some(&Entity::Miner(index)) => {
+        let miner = self.miners[index].clone();
+
+        if miner.alive {
+          self.log(Some(uid), EventKind::Died { item: miner.item, order: miner.order });
+        }
+
+        let miner = &mut self.miners[index];
+        miner.alive = false;
+        miner.order = Order::DEAD;
+        miner.item = None;
+        miner.dug = None;
+        miner.burried = None;
       }
 
-      Some(Entity::OpponentMiner(index)) => {
-        self.opponent_miners[*index].alive = false;
+      Some(&Entity::OpponentMiner(index)) => {
+        self.opponent_miners[index].alive = false;
+        self.dangerous_opponents.remove(&index);
       }
 
-      _ => eprintln!("trying to kill miner {}, but it’s not a miner", uid)
+      _ => self.log(Some(uid), EventKind::Mismatch { update: "death", expected: "miner" })
     }
   }
 
+  /// Mark a burried item as seen this turn.
+  fn see_item(&mut self, uid: UID) {
+    self.seen_items.insert(uid);
+  }
+
+  /// Forget about the burried items we haven’t seen this turn; they have been destroyed, triggered
+  /// or dug back out.
+  fn forget_unseen_items(&mut self) {
+    let seen = &self.seen_items;
+
+    self.burried_radars.retain(|uid, _| seen.contains(uid));
+    self.burried_traps.retain(|uid, _| seen.contains(uid));
+    self.trap_cells = self.burried_traps.values().copied().collect();
+    self.entities.retain(|uid, entity| {
+      match entity {
+        Entity::BurriedRadar | Entity::BurriedTrap => seen.contains(uid),
+        _ => true
+      }
+    });
+    self.seen_items.clear();
+  }
+
   fn burry_radar(&mut self, uid: UID, x: i32, y: i32) {
     self.burried_radars.insert(uid, [x, y]);
   }
 
   fn burry_trap(&mut self, uid: UID, x: i32, y: i32) {
     self.burried_traps.insert(uid, [x, y]);
+    self.trap_cells.insert([x, y]);
   }
 
   fn update_radar_position(&mut self, uid: UID, x: i32, y: i32) {
@@ -364,16 +1008,18 @@ impl GameState {
       p[0] = x;
       p[1] = y;
     } else {
-      eprintln!("trying to update burried radar {} position, but it’s not a radar", uid);
+      self.log(Some(uid), EventKind::Mismatch { update: "position", expected: "radar" });
     }
   }
 
   fn update_trap_position(&mut self, uid: UID, x: i32, y: i32) {
     if let Some(ref mut p) = self.burried_traps.get_mut(&uid) {
+      self.trap_cells.remove(&**p);
+      self.trap_cells.insert([x, y]);
       p[0] = x;
       p[1] = y;
     } else {
-      eprintln!("trying to update burried trap {} position, but it’s not a trap", uid);
+      self.log(Some(uid), EventKind::Mismatch { update: "position", expected: "trap" });
     }
   }
 
@@ -382,11 +1028,7 @@ impl GameState {
   }
 
   fn cell(&self, x: i32, y: i32) -> Option<&Cell> {
-    if x < 0 || x >= self.width as i32 || y < 0 || y >= self.height as i32 {
-      None
-    } else {
-      self.cells.get(y as usize * self.width + x as usize)
-    }
+    self.grid.live(x, y)
   }
 
   // Find the next spot where to put a radar.
@@ -397,9 +1039,11 @@ impl GameState {
     // furthest x pair and see whether we need to add a radar there
 
     // we get the “furthest” radars we’ve placed so far and get the number of radars per x distance
-    // to HQ; we then get the furthest and if its count is < 2, we place our spot there
+    // to HQ; we then get the furthest and if its count is < 2, we place our spot there; radars
+    // currently carried by our miners count as placed, otherwise two carriers would go to the same
+    // spot
     let mut furthest = BTreeMap::<i32, usize>::new();
-    for (_, radar) in &self.burried_radars {
+    for radar in self.burried_radars.values().chain(self.radars_in_flight().iter()) {
       match furthest.entry(radar[0]) {
         Entry::Vacant(v) => {
           v.insert(1);
@@ -441,102 +1085,322 @@ impl GameState {
     }
   }
 
-  fn assign_radar(&mut self) {
-    // keep track of the best choice
-    let mut found = None;
-
-    for (miner_index, miner) in self.miners().enumerate() {
-      if let Some((_, found_dist)) = found {
-        if miner.x < found_dist {
-          found = Some((miner_index, miner.x));
-        }
-      } else {
-        found = Some((miner_index, miner.x));
-      }
-    }
-
-    let (index, _) = found.unwrap();
-    self.miner_with_radar = Some(index);
-    //self.miners[index].order = Order::deploy_radar_to_random(self.width as i32, self.height as i32);
-
-    // instead, we have already access to our burried radars, so let’s just find the next step; each
-    // radar has a radius of 4 units, hence 8 units. The map is 15-cells tall so we just need, in
-    // theory, two radars per column
-    let [radar_x, radar_y] = self.find_best_radar_spot().unwrap();
-    self.miners[index].order = Order::DeployRadarAt(radar_x, radar_y);
-  }
-
-  /// Find the most appealing order to follow.
+  /// Find the best spot to flash a radar at from a given position.
   ///
-  /// If some ore is available, the miner will try to go to the nearest place without overloading
-  /// it. If no ore information is available, the miner will go in a random direction.
-  fn choose_order(&self, miner_index: usize) -> Order {
-    let mut closest_cell = None;
-    let miner = &self.miners[miner_index];
-
-    // FIXME: ensure the cell we’re targetting is not already overcrowded by other miners
-
-    for x in 0 .. self.width {
-      for y in 0 .. self.height {
-        let x = x as i32;
-        let y = y as i32;
+  /// The best spot is the one revealing the most cells we have never observed, penalized by the
+  /// time needed to go there.
+  fn find_best_flash_spot(&self, from: [i32; 2]) -> Option<[i32; 2]> {
+    let mut best = None;
 
-        // prevent digging dangerous cells
+    for x in 1 .. self.width as i32 {
+      for y in 0 .. self.height as i32 {
         if self.is_cell_dangerous(x, y) {
           continue;
         }
 
-        let cell = self.cell(x, y).unwrap();
+        // don’t flash twice at the same place
+        if self.miners().any(|miner| miner.order == Order::FlashRadarAt(x, y)) {
+          continue;
+        }
+
+        let mut unknown = 0;
+        for rx in x - 4 ..= x + 4 {
+          for ry in y - 4 ..= y + 4 {
+            let unseen = self.grid.snapshot(rx, ry).map(|cell| cell.seen_at.is_none()).unwrap_or(false);
 
-        match cell.ore_amount {
-          Some(ore_amount) if ore_amount > 0 => {
-            if let Some((cx, cy, _)) = closest_cell {
-              if manh_dist([x, y], [miner.x, miner.y]) < manh_dist([cx, cy], [miner.x, miner.y]) {
-                closest_cell = Some((x, y, ore_amount));
-              }
-            } else {
-              closest_cell = Some((x, y, ore_amount));
+            if rx > 0 && unseen && manh_dist([x, y], [rx, ry]) <= 4 {
+              unknown += 1;
             }
           }
-
-          _ => ()
         }
-      }
-    }
 
-    if let Some((x, y, _)) = closest_cell {
-      // we don’t really want to go to that cell directly; we want to dig “nearest” to that cell;
-      // this part of code finds the solution to that problem
-      let mut solution = [x, y];
-
-      for i in &[-1, 1] {
-        let candidate = [x + i, y];
-        if self.cell(candidate[0], candidate[1]).is_some() {
-          if manh_dist([miner.x, miner.y], candidate) < manh_dist([miner.x, miner.y], solution) {
-            solution = candidate;
-          }
+        if unknown < FLASH_MIN_UNKNOWN_CELLS {
+          continue;
         }
 
-        let candidate = [x, y + i];
-        if self.cell(candidate[0], candidate[1]).is_some() {
-          if manh_dist([miner.x, miner.y], candidate) < manh_dist([miner.x, miner.y], solution) {
-            solution = candidate;
-          }
+        let score = unknown - turns_to_reach(from, [x, y]) as i32 * MINER_SPEED;
+
+        if best.map(|(_, best_score)| score > best_score).unwrap_or(true) {
+          best = Some(([x, y], score));
         }
       }
+    }
 
-      Order::DigAt(x, y, solution[0], solution[1])
-    } else {
-      // loop until we find a safe destination; if we cannot find any, we’ll just wait (it’s a
-      // bad situation but it’s safer than being killed)
-      let mut final_order = None;
+    best.map(|(spot, _)| spot)
+  }
 
-      for _ in 0 .. MAX_RANDOM_SAFE_DEST_TRIES {
-        //let order = Order::go_to_random(self.width as i32, self.height as i32);
-        let order = Order::go_to_restricted_random(self.width as i32, self.height as i32, self.exploration_distance);
-        let [x, y] = order.destination();
+  /// Destinations of all the radars our carriers are currently taking care of.
+  fn radars_in_flight(&self) -> Vec<[i32; 2]> {
+    self.miners().filter_map(|miner| {
+      match miner.order {
+        Order::DeployRadarAt(x, y) if miner.alive => Some([x, y]),
+        _ => None
+      }
+    }).collect()
+  }
 
-        if !self.is_cell_dangerous(x, y) {
+  /// Number of miners carrying (or going to pick) a given item.
+  fn carriers_count(&self, item: RequestItem) -> usize {
+    self.miners().filter(|miner| miner.alive && miner.order.deployed_item() == Some(item)).count()
+  }
+
+  /// Current cooldown of an item.
+  fn item_cooldown(&self, item: RequestItem) -> u32 {
+    match item {
+      RequestItem::Radar => self.radar_cooldown,
+      RequestItem::Trap => self.trap_cooldown,
+    }
+  }
+
+  /// Number of items of a given kind we would like to have.
+  fn wanted_items(&self, item: RequestItem) -> usize {
+    // there’s no time to deploy anything at the end of the game
+    if self.is_endgame() {
+      return 0;
+    }
+
+    match item {
+      RequestItem::Radar if RADAR_FLASHING => {
+        // flashed radars are dug back out, so we only need a few carriers recycling them
+        if self.visible_ore_amount() < self.params.min_ore_no_radar_needed {
+          MAX_FLASHING_CARRIERS.saturating_sub(self.carriers_count(RequestItem::Radar))
+        } else {
+          0
+        }
+      }
+
+      RequestItem::Radar => {
+        let radars = self.burried_radars.len() + self.carriers_count(RequestItem::Radar);
+
+        if radars < self.params.max_radars && self.visible_ore_amount() < self.params.min_ore_no_radar_needed {
+          self.params.max_radars - radars
+        } else {
+          0
+        }
+      }
+
+      RequestItem::Trap => self.trap_targets.len(),
+    }
+  }
+
+  /// Plan the item pickups for the next LOGISTICS_HORIZON turns.
+  ///
+  /// Pickups are spread according to the current cooldowns: once an item is requested, the next
+  /// one of the same kind will only be available ITEM_COOLDOWN turns later.
+  fn plan_pickups(&self) -> Vec<ItemPickup> {
+    let mut plan = Vec::new();
+
+    for &item in &[RequestItem::Radar, RequestItem::Trap] {
+      // a carrier that is already heading to HQ will consume the first available item
+      let pending = self.miners().filter(|miner| {
+        miner.alive && miner.order.deployed_item() == Some(item) && miner.item != Some(item.into())
+      }).count() as u32;
+      let mut turn = self.item_cooldown(item) + pending * ITEM_COOLDOWN;
+
+      for _ in 0 .. self.wanted_items(item) {
+        if turn > LOGISTICS_HORIZON {
+          break;
+        }
+
+        plan.push(ItemPickup { item, turn });
+        turn += ITEM_COOLDOWN;
+      }
+    }
+
+    plan
+  }
+
+  /// Assign carriers to the planned pickups.
+  ///
+  /// A carrier is assigned a pickup only if it will reach HQ around the time the item is
+  /// available. Miners already going back to HQ to deliver ore are preferred, as they can pick
+  /// the item on the same visit.
+  fn schedule_items(&mut self) {
+    // release radar carriers that haven’t picked their radar yet if we don’t need it anymore
+    if self.visible_ore_amount() >= self.params.min_ore_no_radar_needed || self.is_endgame() {
+      for miner_index in 0 .. self.miners.len() {
+        let miner = &self.miners[miner_index];
+
+        if miner.order.deployed_item() == Some(RequestItem::Radar) && miner.item != Some(Item::Radar) {
+          self.miners[miner_index].order = self.choose_order(miner_index);
+        }
+      }
+    }
+
+    // same thing for trap carriers at the end of the game
+    if self.is_endgame() {
+      for miner_index in 0 .. self.miners.len() {
+        let miner = &self.miners[miner_index];
+
+        if miner.order.deployed_item() == Some(RequestItem::Trap) && miner.item != Some(Item::Trap) {
+          self.miners[miner_index].order = self.choose_order(miner_index);
+        }
+      }
+    }
+
+    self.pickup_schedule = self.plan_pickups();
+
+    for pickup in self.pickup_schedule.clone() {
+      let mut best = None;
+
+      for (miner_index, miner) in self.miners().enumerate() {
+        if !miner.alive || miner.role != Role::carrying(pickup.item) || miner.order.deployed_item().is_some() {
+          continue;
+        }
+
+        let eta = turns_to_reach([miner.x, miner.y], [0, miner.y]);
+
+        // don’t make the miner wait too long at HQ
+        if eta + MAX_PICKUP_WAIT < pickup.turn {
+          continue;
+        }
+
+        let lateness = eta.saturating_sub(pickup.turn);
+        let detour = if let Order::Deliver(..) = miner.order { 0 } else { eta };
+        let cost = lateness + detour;
+
+        if best.map(|(_, best_cost)| cost < best_cost).unwrap_or(true) {
+          best = Some((miner_index, cost));
+        }
+      }
+
+      if let Some((miner_index, _)) = best {
+        let order = match pickup.item {
+          RequestItem::Radar if RADAR_FLASHING => {
+            self.find_best_flash_spot([0, self.miners[miner_index].y]).map(|[x, y]| Order::FlashRadarAt(x, y))
+          }
+          RequestItem::Radar => self.find_best_radar_spot().map(|[x, y]| Order::DeployRadarAt(x, y)),
+          RequestItem::Trap => self.trap_targets.pop().map(|[x, y]| Order::DeployTrapAt(x, y)),
+        };
+
+        if let Some(order) = order {
+          let uid = self.miners[miner_index].uid;
+          self.log(Some(uid), EventKind::PickupScheduled { item: pickup.item, turns: pickup.turn });
+          self.miners[miner_index].order = order;
+        }
+      }
+    }
+  }
+
+  /// Rebalance the roles of our miners.
+  ///
+  /// Miners busy with an order keep the matching role; the others prospect unless we need more
+  /// specialists: radar runners and trappers for the pickups planned according to the cooldowns,
+  /// the known ore and the mode, and guards for the burials worth destroying. At most half of the
+  /// living miners (at least one) are specialists, so that someone keeps mining.
+  fn allocate_roles(&mut self) {
+    let alive = self.miners().filter(|miner| miner.alive).count();
+    let max_specialists = (alive / 2).max(1);
+
+    for miner in &mut self.miners {
+      miner.role = if miner.alive { miner.order.role() } else { Role::Prospector };
+    }
+
+    let mut specialists = self.miners().filter(|miner| miner.alive && miner.role != Role::Prospector).count();
+
+    for pickup in self.plan_pickups() {
+      if specialists >= max_specialists {
+        return;
+      }
+
+      // the miner that would get to HQ first, the ones delivering ore being already on their way
+      let candidate = self.miners().enumerate()
+        .filter(|(_, miner)| miner.alive && miner.role == Role::Prospector)
+        .map(|(miner_index, miner)| {
+          let detour = if let Order::Deliver(..) = miner.order { 0 } else { turns_to_reach([miner.x, miner.y], [0, miner.y]) };
+          (miner_index, detour)
+        })
+        .min_by_key(|&(_, detour)| detour);
+
+      if let Some((miner_index, _)) = candidate {
+        let uid = self.miners[miner_index].uid;
+        self.log(Some(uid), EventKind::RoleChanged(Role::carrying(pickup.item)));
+        self.miners[miner_index].role = Role::carrying(pickup.item);
+        specialists += 1;
+      }
+    }
+
+    for cell in self.destruction_targets() {
+      if specialists >= max_specialists {
+        return;
+      }
+
+      if let Some(miner_index) = self.closest_destroyer(Role::Prospector, cell) {
+        let uid = self.miners[miner_index].uid;
+        self.log(Some(uid), EventKind::RoleChanged(Role::Guard));
+        self.miners[miner_index].role = Role::Guard;
+        specialists += 1;
+      }
+    }
+  }
+
+  /// Random order going to the current exploration window.
+  fn random_exploration_order(&self) -> Order {
+    Order::go_to_restricted_random(self.width as i32, self.height as i32, self.exploration_distance, self.params.exploration_delta_distance)
+  }
+
+  /// Find the most appealing order to follow.
+  ///
+  /// If some ore is available, the miner will try to go to the nearest place without overloading
+  /// it. If no ore information is available, the miner will go in a random direction.
+  fn choose_order(&self, miner_index: usize) -> Order {
+    let mut closest_cell = None;
+    let miner = &self.miners[miner_index];
+
+    // FIXME: ensure the cell we’re targetting is not already overcrowded by other miners
+
+    // there’s no ore at HQ
+    for x in 1 .. self.width {
+      for y in 0 .. self.height {
+        let x = x as i32;
+        let y = y as i32;
+
+        // prevent digging dangerous cells
+        if self.is_cell_risky(x, y) {
+          continue;
+        }
+
+        // at the end of the game, only go for the ore we have time to deliver
+        if self.is_endgame() && delivery_turns([miner.x, miner.y], [x, y]) > self.remaining_turns() {
+          continue;
+        }
+
+        // we favor the veins we are racing an opponent for, even if we don’t know what’s in there,
+        // and the ore the opponent likely sees with their radars
+        let racing = self.is_racing(x, y);
+        let candidate = match self.known_ore(x, y) {
+          Some(ore_amount) => ore_amount > 0 && self.expected_ore(x, y) >= MIN_EXPECTED_ORE,
+          None => racing
+        };
+        let raced = racing || (candidate && self.likely_known_by_opponent(x, y));
+        let dist = manh_dist([x, y], [miner.x, miner.y]);
+
+        // raced cells look closer than they are, but never closer than the miner itself; the actual
+        // distance breaks the ties
+        let bonus = if raced { self.params.race_distance_bonus } else { 0 };
+        let score = ((dist - bonus).max(0), dist);
+
+        if candidate && closest_cell.map(|(_, _, closest_score)| score < closest_score).unwrap_or(true) {
+          closest_cell = Some((x, y, score));
+        }
+      }
+    }
+
+    if let Some((x, y, _)) = closest_cell {
+      let [sx, sy] = self.dig_spot([miner.x, miner.y], x, y);
+      Order::DigAt(x, y, sx, sy)
+    } else {
+      // loop until we find a safe destination; if we cannot find any, we’ll just wait (it’s a
+      // bad situation but it’s safer than being killed)
+      let mut final_order = None;
+
+      for _ in 0 .. self.params.max_random_safe_dest_tries {
+        //let order = Order::go_to_random(self.width as i32, self.height as i32);
+        let order = self.random_exploration_order();
+        let [x, y] = order.destination();
+        let too_late = self.is_endgame() && delivery_turns([miner.x, miner.y], [x, y]) > self.remaining_turns();
+
+        if !self.is_cell_dangerous(x, y) && !too_late {
           final_order = Some(order);
         }
       }
@@ -545,6 +1409,57 @@ impl GameState {
     }
   }
 
+  /// Number of turns left in the game, including the current one.
+  fn remaining_turns(&self) -> u32 {
+    MAX_TURNS.saturating_sub(self.turn)
+  }
+
+  /// Whether we are in the last turns of the game, when only the ore we can deliver in time
+  /// matters.
+  fn is_endgame(&self) -> bool {
+    self.remaining_turns() <= ENDGAME_TURNS
+  }
+
+  /// At the end of the game, make sure every miner carrying ore delivers it right away.
+  fn prioritize_deliveries(&mut self) {
+    if !self.is_endgame() {
+      return;
+    }
+
+    for miner in &mut self.miners {
+      if miner.alive && miner.item == Some(Item::Ore) {
+        if let Order::Deliver(..) = miner.order {
+          continue;
+        }
+
+        miner.order = Order::Deliver(miner.x, miner.y);
+      }
+    }
+  }
+
+  /// Find the cell from which to dig a given cell.
+  fn dig_spot(&self, from: [i32; 2], x: i32, y: i32) -> [i32; 2] {
+    // we don’t really want to go to that cell directly; we want to dig “nearest” to that cell;
+    // this part of code finds the solution to that problem
+    let mut solution = [x, y];
+
+    for i in &[-1, 1] {
+      let candidate = [x + i, y];
+      if self.cell(candidate[0], candidate[1]).is_some()
+        && manh_dist(from, candidate) < manh_dist(from, solution) {
+        solution = candidate;
+      }
+
+      let candidate = [x, y + i];
+      if self.cell(candidate[0], candidate[1]).is_some()
+        && manh_dist(from, candidate) < manh_dist(from, solution) {
+        solution = candidate;
+      }
+    }
+
+    solution
+  }
+
   /// Update the window distances we are willing to send miners. Updating that value depends on
   /// several things:
   ///
@@ -554,13 +1469,13 @@ impl GameState {
   fn update_exploration_distances(&mut self) {
     if self.visible_ore_amount() == 0 {
       // compute the number of cells there is in the patch we are digging
-      let cells_count = self.height as i32 / 2 * EXPLORATION_DELTA_DISTANCE;
+      let cells_count = self.height as i32 / 2 * self.params.exploration_delta_distance;
       let mut holes = 0;
 
-      for x in self.exploration_distance .. (self.exploration_distance + EXPLORATION_DELTA_DISTANCE).min(self.width as i32) {
+      for x in self.exploration_distance .. (self.exploration_distance + self.params.exploration_delta_distance).min(self.width as i32) {
         for y in 0 .. self.height as i32 {
           // for all those cells, look for the number of cells we know we have digged
-          if self.cells_snapshot[y as usize * self.width + x as usize].has_hole {
+          if self.grid.snapshot(x, y).map(|cell| cell.has_hole).unwrap_or(false) {
             holes += 1;
           }
         }
@@ -571,31 +1486,35 @@ impl GameState {
       let exploration_ratio = holes as f32 / cells_count as f32;
 
       // if it’s over the threshold, we need to explore another patch
-      if exploration_ratio >= EXPLORATION_RATIO_THRESHOLD {
-        self.exploration_distance += EXPLORATION_DELTA_DISTANCE;
-
-        eprintln!("new exploration window: {} -> {}", self.exploration_distance, self.exploration_distance + EXPLORATION_DELTA_DISTANCE);
+      if exploration_ratio >= self.params.exploration_ratio_threshold {
+        let from = self.exploration_distance;
+        self.exploration_distance += self.params.exploration_delta_distance;
+        self.log(None, EventKind::ExplorationWindow { from, to: self.exploration_distance });
       }
     }
   }
 
   /// Total amount of ore we know about.
   fn visible_ore_amount(&self) -> usize {
-    let mut amount = 0;
+    let mut amount = 0.;
 
     for x in 0 .. self.width {
       for y in 0 .. self.height {
         if !self.is_cell_dangerous(x as i32, y as i32) {
-          amount += self.cells[y * self.width + x].ore_amount.unwrap_or(0);
+          amount += self.expected_ore(x as i32, y as i32);
         }
       }
     }
 
-    amount
+    amount as usize
   }
 
   /// Prepare the next turn by updating what must be updated.
   fn setup_next_turn(&mut self) {
+    self.turn += 1;
+    self.grid.next_turn();
+    self.events.clear();
+
     // update “previous” position of miners to be able to compute velocities
     for miner in &mut self.miners {
       miner.prev_xy = Some([miner.x, miner.y]);
@@ -614,7 +1533,11 @@ impl GameState {
     // to ABORT mission and regenerate a new order; this might happen if we received the order
     // from far away and the opponent detected that we wanted to go at that place and put a trap
     // while we were commutting (fuck them)
-    if self.is_cell_dangerous(dig_x, dig_y) {
+    //
+    // the same goes at the end of the game if we don’t have time to deliver what we’d dig anymore
+    let too_late = self.is_endgame() && delivery_turns([miner.x, miner.y], [dig_x, dig_y]) > self.remaining_turns();
+
+    if self.is_cell_dangerous(dig_x, dig_y) || too_late {
       let order = self.choose_order(miner_index);
       let [dx, dy] = order.destination();
 
@@ -626,15 +1549,20 @@ impl GameState {
     if manh_dist([x, y], [miner.x, miner.y]) == 0 {
       // we arrived at our destination, so let’s inspect the cell
       let cell = self.cell(dig_x, dig_y).unwrap();
+      let known_ore = self.known_ore(dig_x, dig_y);
 
       if miner.item == Some(Item::Ore) {
         // we just digged some ore; get back to the HQ
         self.miners[miner_index].order = Order::Deliver(miner.x, miner.y);
         Request::back_to_hq([miner.x, miner.y])
-      } else if cell.ore_amount.is_none() && !cell.has_hole {
-        // case of an unknown cell with no hole; we are there so we just dig to check
+      } else if known_ore.is_none() && (!cell.has_hole || self.is_racing(dig_x, dig_y)) {
+        // case of an unknown cell with no hole (or one the opponent is mining); we are there so we
+        // just dig to check
+        Request::Dig(dig_x, dig_y)
+      } else if known_ore.unwrap_or(0) > 0 && !cell.is_live(self.turn) {
+        // we only know the last value of that cell, so we dig and check whether we got some ore
         Request::Dig(dig_x, dig_y)
-      } else if cell.ore_amount.unwrap_or(0) > 0 {
+      } else if known_ore.unwrap_or(0) > 0 {
         // the current cell has some ore so we dig it
         self.miners[miner_index].order = Order::Deliver(miner.x, miner.y);
         Request::Dig(dig_x, dig_y)
@@ -665,389 +1593,2430 @@ impl GameState {
     }
   }
 
-  /// Check whether a cell is dangerous.
-  fn is_cell_dangerous(&self, x: i32, y: i32) -> bool {
-    self.dangerous_cells.contains_key(&[x, y])
-  }
-
-  /// Tag dangerous cells safe if we’re sure they’re not dangerous anymore.
-  fn retag_safe_cells(&mut self) {
-    let cells = &self.cells_snapshot;
-    let width = self.width;
-
-    self.dangerous_cells.retain(|[dx, dy], dangerous_cell| {
-      // a cell is dangerous only if the current amount of ore is still the same number as when it
-      // was marked dangerous
-      let r = cells[*dy as usize * width + *dx as usize].ore_amount == dangerous_cell.ore_amount;
-      if !r {
-        eprintln!("cell ({}, {}) is actually safe!", dx, dy);
-      }
-      r
-    });
-  }
-}
+  /// Implement item deploying orders.
+  ///
+  /// The carrier goes back to HQ, requests the item as soon as it’s available and then burries it
+  /// at its destination.
+  fn order_deploy(&mut self, miner_index: usize, item: RequestItem, x: i32, y: i32) -> RequestComment {
+    let mut miner = self.miners[miner_index].clone();
 
-/// Describe a single cell on the grid.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-struct Cell {
-  ore_amount: Option<usize>,
-  has_hole: bool
-}
+    // the spot might have been trapped while we were on our way; burry the item next to it instead
+    let [x, y] = if self.is_cell_dangerous(x, y) {
+      let spot = self.safe_spot_around(x, y).unwrap_or([x, y]);
 
-impl Default for Cell {
-  fn default() -> Self {
-    Cell {
-      ore_amount: None,
-      has_hole: false,
-    }
-  }
-}
+      miner.order = miner.order.with_destination(spot[0], spot[1]);
+      self.miners[miner_index].order = miner.order;
 
-impl fmt::Display for Cell {
-  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    let hole = if self.has_hole { 'o' } else { 'x' };
-    let ore_amount = if let Some(ore_amount) = self.ore_amount {
-      format!("{:2}", ore_amount)
+      spot
     } else {
-      "  ".to_owned()
+      [x, y]
     };
 
-    write!(f, "{}{}", hole, ore_amount)?;
-
-    Ok(())
-  }
-}
+    if miner.item == Some(item.into()) {
+      // if that unit has already the item
+      if is_cell_at_range([x, y], [miner.x, miner.y]) {
+        // if we arrived at destination, just burry the item; flashed radars will be dug back out
+        // on the next turn
+        self.miners[miner_index].order = if let Order::FlashRadarAt(..) = miner.order {
+          Order::RecoverRadarAt(x, y)
+        } else {
+          self.choose_order(miner_index)
+        };
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-struct Miner {
-  x: i32,
-  y: i32,
-  prev_xy: Option<[i32; 2]>,
-  item: Option<Item>,
-  uid: UID,
-  alive: bool,
-  order: Order,
-}
+        Request::Dig(x, y).into()
+      } else {
+        // otherwise, go there
+        Request::Move(x, y).comment(format!("{} -> ({}, {})", item, x, y))
+      }
+    } else if miner.x != 0 {
+      // go home to ask for the item
+      Request::back_to_hq([miner.x, miner.y]).comment(format!("fetch {}", item))
+    } else if self.item_cooldown(item) == 0 {
+      // ask the item; other carriers will have to wait for the cooldown
+      match item {
+        RequestItem::Radar => self.radar_cooldown = ITEM_COOLDOWN,
+        RequestItem::Trap => self.trap_cooldown = ITEM_COOLDOWN,
+      }
 
-impl Miner {
-  /// Velocity of the miner as a gradient.
-  fn velocity_gradient(&self) -> [i32; 2] {
-    let [px, py] = self.prev_xy.unwrap_or([self.x, self.y]);
-    [self.x - px, self.y - py]
+      Request::Item(item).into()
+    } else {
+      // the item is not available yet
+      Request::Wait.comment(format!("wait {}", item))
+    }
   }
-}
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-enum Order {
-  /// Do just nothing.
-  Stay(i32, i32),
-  /// Move to a given unit.
-  ///
-  /// This is an exploration mode. If a better alternative is found, this unit should abort this
-  /// order.
-  GoTo(i32, i32),
-  /// Move to a cell to dig from.
+  /// Implement the recovery of a flashed radar.
   ///
-  /// The first two i32 is the actual ore cell to dig. The last two i32 is the cell from which
-  /// we should dig (the destination).
-  DigAt(i32, i32, i32, i32),
-  DeployRadarAt(i32, i32),
-  Deliver(i32, i32),
-}
+  /// At that point, the radar has revealed the ore around it and the values are stored in the
+  /// snapshot, so we dig it back out and flash it somewhere else. The official referee never gives
+  /// radars back though: a radar still there after we dug it (or while we carry the ore of its
+  /// cell) is left deployed for good.
+  fn order_recover_radar(&mut self, miner_index: usize, x: i32, y: i32) -> RequestComment {
+    let miner = self.miners[miner_index].clone();
+    let still_wanted = self.visible_ore_amount() < self.params.min_ore_no_radar_needed;
+    let radar_kept = self.burried_radars.values().any(|&radar| radar == [x, y]);
+
+    if radar_kept && miner.dug != Some([x, y]) && miner.item != Some(Item::Ore) {
+      // the radar is still there; dig it back out
+      Request::Dig(x, y).comment("recover RADAR")
+    } else if radar_kept {
+      // the radar won’t come back; leave it there and get back to mining
+      let (order, request) = if miner.item == Some(Item::Ore) {
+        (Order::Deliver(miner.x, miner.y), Request::back_to_hq([miner.x, miner.y]))
+      } else {
+        let order = self.choose_order(miner_index);
+        let [dx, dy] = order.destination();
+        (order, Request::Move(dx, dy))
+      };
 
-impl Order {
-  /// A version of go_to_random restricted by a window
-  fn go_to_restricted_random(width: i32, height: i32, exploration_distance: i32) -> Self {
-    let mut rng = thread_rng();
+      self.miners[miner_index].order = order;
+      request.comment("keep RADAR")
+    } else if miner.item == Some(Item::Radar) {
+      // we have the radar back; flash it elsewhere if we still need some ore information, or deploy
+      // it for good
+      let order = if still_wanted {
+        self.find_best_flash_spot([miner.x, miner.y]).map(|[x, y]| Order::FlashRadarAt(x, y))
+      } else {
+        None
+      };
+      let order = order
+        .or_else(|| self.find_best_radar_spot().map(|[x, y]| Order::DeployRadarAt(x, y)))
+        .unwrap_or(Order::DeployRadarAt(x, y));
 
-    Order::GoTo(
-      rng.gen_range(exploration_distance, (exploration_distance + EXPLORATION_DELTA_DISTANCE).min(width)),
-      rng.gen_range(0, height)
-    )
-  }
+      self.miners[miner_index].order = order;
+      let [dx, dy] = order.destination();
 
-  /// Deploy a radar at a random location; we prevent burrying the radar too close to edges because
-  /// it would be a waste.
-  fn deploy_radar_to_random(width: i32, height: i32) -> Self {
-    let mut rng = thread_rng();
-    Order::DeployRadarAt(rng.gen_range(3, width - 3), rng.gen_range(3, height - 3))
-  }
+      Request::Move(dx, dy).comment("flash RADAR")
+    } else {
+      // the radar is lost (either destroyed or the referee didn’t give it back); go get another one
+      // if needed
+      let order = if still_wanted {
+        self.find_best_flash_spot([0, miner.y]).map(|[x, y]| Order::FlashRadarAt(x, y))
+      } else {
+        None
+      };
+      let order = order.unwrap_or_else(|| self.choose_order(miner_index));
 
-  fn destination(&self) -> [i32; 2] {
-    match *self {
-      Order::Stay(x, y) => [x, y],
-      Order::GoTo(x, y) => [x, y],
-      Order::DigAt(_, _, x, y) => [x, y],
-      Order::DeployRadarAt(x, y) => [x, y],
-      Order::Deliver(x, y) => [x, y],
-    }
-  }
+      self.miners[miner_index].order = order;
+      let [dx, dy] = order.destination();
 
-  fn is_random(&self) -> bool {
-    if let Order::GoTo(..) = *self {
-      true
-    } else {
-      false
+      Request::Move(dx, dy).into()
     }
   }
 
-  fn is_digging_order(&self) -> bool {
-    if let Order::DigAt(..) = *self {
-      true
-    } else {
-      false
-    }
-  }
-}
+  /// Find the closest safe cell around a given cell, HQ excluded.
+  fn safe_spot_around(&self, x: i32, y: i32) -> Option<[i32; 2]> {
+    let mut best = None;
 
-fn main() {
-  let mut input_line = String::new();
-  io::stdin().read_line(&mut input_line).unwrap();
-  let inputs = input_line.split(" ").collect::<Vec<_>>();
+    for sx in x - 2 ..= x + 2 {
+      for sy in y - 2 ..= y + 2 {
+        let dist = manh_dist([x, y], [sx, sy]);
 
-  let width = parse_input!(inputs[0], i32);
-  let height = parse_input!(inputs[1], i32); // size of the map
+        if sx <= 0 || dist > 2 || self.cell(sx, sy).is_none() || self.is_cell_dangerous(sx, sy) {
+          continue;
+        }
 
-  let mut game_state = GameState::new(width as usize, height as usize);
+        if best.map(|(_, best_dist)| dist < best_dist).unwrap_or(true) {
+          best = Some(([sx, sy], dist));
+        }
+      }
+    }
 
-  // game loop
-  let mut turn: usize = 0;
+    best.map(|(spot, _)| spot)
+  }
 
-  loop {
-    let mut input_line = String::new();
-    io::stdin().read_line(&mut input_line).unwrap();
-    let inputs = input_line.split(" ").collect::<Vec<_>>();
+  /// Implement the destruction of a suspected opponent burial.
+  fn order_destroy(&mut self, miner_index: usize, x: i32, y: i32, from_x: i32, from_y: i32) -> RequestComment {
+    let miner = self.miners[miner_index].clone();
+    let worth_it = self.burial_suspects.iter().any(|suspect| {
+      suspect.exact && suspect.cell == [x, y] && suspect.waits < BURIAL_DESTROY_MAX_WAITS && self.destruction_value(suspect) > 0.
+    });
+    let at_spot = [miner.x, miner.y] == [from_x, from_y] || is_cell_at_range([x, y], [miner.x, miner.y]);
+
+    if !worth_it || miner.item.is_some() {
+      let order = self.choose_order(miner_index);
+      let [dx, dy] = order.destination();
+
+      self.miners[miner_index].order = order;
+
+      Request::Move(dx, dy).into()
+    } else if at_spot && self.other_miner_adjacent(miner_index, [x, y]) {
+      // if it’s a trap, we don’t want to blow up someone else with us; if they don’t move away in
+      // time, the burial is given up on the next turn
+      for suspect in self.burial_suspects.iter_mut().filter(|suspect| suspect.exact && suspect.cell == [x, y]) {
+        suspect.waits += 1;
+      }
+
+      Request::Wait.comment("destroy: wait")
+    } else if at_spot {
+      self.miners[miner_index].order = self.choose_order(miner_index);
+
+      Request::Dig(x, y).comment("destroy")
+    } else {
+      Request::Move(from_x, from_y).comment(format!("destroy ({}, {})", x, y))
+    }
+  }
+
+  /// Check whether a cell is dangerous.
+  ///
+  /// Our own traps are dangerous too.
+  fn is_cell_dangerous(&self, x: i32, y: i32) -> bool {
+    self.dangerous_cells.contains_key(&[x, y]) || self.trap_cells.contains(&[x, y])
+  }
+
+  /// Check whether digging a cell is too risky for the current mode.
+  ///
+  /// When playing safe, cells next to a dangerous cell are avoided too, since a trap triggered by
+  /// someone else would also kill us.
+  fn is_cell_risky(&self, x: i32, y: i32) -> bool {
+    if self.is_cell_dangerous(x, y) {
+      return true;
+    }
+
+    self.mode == Mode::SafeMining && [[-1, 0], [1, 0], [0, -1], [0, 1]].iter().any(|[dx, dy]| {
+      self.is_cell_dangerous(x + dx, y + dy)
+    })
+  }
+
+  /// Switch the strategic mode according to the score delta, the remaining turns and the known
+  /// ore.
+  fn update_mode(&mut self) {
+    let delta = self.my_score as i64 - self.opponent_score as i64;
+    let margin = 1 + (self.remaining_turns() / SCORE_MARGIN_TURNS) as i64;
+    let exhausted = self.burried_radars.len() >= self.params.max_radars || self.find_best_radar_spot().is_none();
+    let scarce = self.visible_ore_amount() < self.params.scarce_ore_amount && (exhausted || self.turn >= MAX_TURNS / 2);
+
+    let mode = if scarce {
+      Mode::Denial
+    } else if delta >= margin {
+      Mode::SafeMining
+    } else if delta <= -margin {
+      Mode::Aggressive
+    } else {
+      Mode::Balanced
+    };
 
-    let my_score = parse_input!(inputs[0], u32); // Amount of ore delivered
-    let opponent_score = parse_input!(inputs[1], u32);
+    if mode != self.mode {
+      self.log(None, EventKind::ModeSwitched { from: self.mode, to: mode, delta, margin });
+      self.mode = mode;
+    }
+  }
+
+  /// Find the best cell to burry a trap in.
+  ///
+  /// We want ore cells the opponents will dig, so we pick the one with enough ore that is the
+  /// closest to them.
+  fn find_best_trap_spot(&self) -> Option<[i32; 2]> {
+    let mut best = None;
+
+    for x in 1 .. self.width as i32 {
+      for y in 0 .. self.height as i32 {
+        if self.known_ore(x, y).unwrap_or(0) < MIN_TRAP_ORE || self.is_cell_dangerous(x, y) {
+          continue;
+        }
 
-    game_state.set_my_score(my_score);
-    game_state.set_opponent_score(opponent_score);
+        // don’t destroy our own radars nor trap twice the same cell
+        let targeted = self.trap_targets.contains(&[x, y]) || self.miners().any(|miner| miner.order == Order::DeployTrapAt(x, y));
 
-    for y in 0 .. height as usize {
-      let mut input_line = String::new();
-      io::stdin().read_line(&mut input_line).unwrap();
-      let inputs = input_line.split_whitespace().collect::<Vec<_>>();
+        if self.burried_radars.values().any(|&radar| radar == [x, y]) || targeted {
+          continue;
+        }
 
-      // we skip x = 0 as it’s HQ
-      for x in 1 .. width as usize {
-        let ore: Option<usize> = inputs[2 * x].trim().parse().ok(); // amount of ore or "?" if unknown
-        let hole = parse_input!(inputs[2 * x + 1], u32) == 1; // 1 if cell has a hole
+        let dist: i32 = self.opponent_miners.iter()
+          .filter(|opponent| opponent.alive)
+          .map(|opponent| manh_dist([opponent.x, opponent.y], [x, y]))
+          .sum();
 
-        game_state.update_cell(x, y, ore, hole);
+        if best.map(|(_, best_dist)| dist < best_dist).unwrap_or(true) {
+          best = Some(([x, y], dist));
+        }
       }
     }
 
-    let mut input_line = String::new();
-    io::stdin().read_line(&mut input_line).unwrap();
-    let inputs = input_line.split(" ").collect::<Vec<_>>();
+    best.map(|(spot, _)| spot)
+  }
 
-    let entity_count = parse_input!(inputs[0], u32); // number of entities visible to you
-    let radar_cooldown = parse_input!(inputs[1], u32); // turns left until a new radar can be requested
-    let trap_cooldown = parse_input!(inputs[2], u32); // turns left until a new trap can be requested
-
-    game_state.set_radar_cooldown(radar_cooldown);
-    game_state.set_trap_cooldown(trap_cooldown);
-
-    for _ in 0..entity_count as usize {
-      let mut input_line = String::new();
-      io::stdin().read_line(&mut input_line).unwrap();
-      let inputs = input_line.split(" ").collect::<Vec<_>>();
-
-      let uid = parse_input!(inputs[0], u32); // unique id of the entity
-      let entity_type: EntityType = parse_input!(inputs[1], u32).try_into().unwrap();
-
-      let x = parse_input!(inputs[2], i32);
-      let y = parse_input!(inputs[3], i32); // position of the entity
-      let item = parse_input!(inputs[4], i32).try_into().ok(); // if this entity is a robot, the item it is carrying (-1 for NONE, 2 for RADAR, 3 for TRAP, 4 for ORE)
-
-      // check if we need to update our entities
-      if !game_state.entity_exists(uid) {
-        // if it’s a miner, add it to the list of miners
-        match entity_type {
-          EntityType::Miner => {
-            let miner_index = game_state.add_miner(Miner {
-              x,
-              y,
-              prev_xy: None,
-              item,
-              uid,
-              alive: true,
-              order: Order::go_to_restricted_random(width, height, game_state.exploration_distance),
-            });
-
-            game_state.add_entity(uid, Entity::Miner(miner_index));
-          }
+  /// Add trap targets if the current mode or the contested veins require traps.
+  fn plan_traps(&mut self) {
+    if self.is_endgame() {
+      self.trap_targets.clear();
+      return;
+    }
 
-          EntityType::OpponentMiner => {
-            let opponent_miner_index = game_state.add_opponent_miner(Miner {
-              x,
-              y,
-              prev_xy: None,
-              item,
-              uid,
-              alive: true,
-              order: Order::Stay(x, y),
-            });
-
-            game_state.add_entity(uid, Entity::OpponentMiner(opponent_miner_index));
-          }
+    let traps = self.burried_traps.len() + self.carriers_count(RequestItem::Trap) + self.trap_targets.len();
 
-          EntityType::BurriedRadar => {
-            game_state.add_entity(uid, Entity::BurriedRadar);
-            game_state.burry_radar(uid, x, y);
-          }
+    if traps >= self.params.max_traps || !self.trap_targets.is_empty() {
+      return;
+    }
 
-          EntityType::BurriedTrap => {
-            game_state.add_entity(uid, Entity::BurriedTrap);
-            game_state.burry_trap(uid, x, y);
-          }
+    // veins we cannot win against the opponent come first
+    let vein_spot = self.contested_veins.iter()
+      .filter(|(_, &decision)| decision == VeinDecision::Trap)
+      .filter_map(|(&stop, _)| self.find_vein_trap_spot(stop))
+      .next();
+
+    let spot = if vein_spot.is_some() {
+      vein_spot
+    } else if self.mode.uses_traps() {
+      self.find_best_trap_spot()
+    } else {
+      None
+    };
+
+    if let Some(spot) = spot {
+      self.trap_targets.push(spot);
+    }
+  }
+
+  /// Record an opponent stopping somewhere to dig.
+  ///
+  /// Only stops next to a hole count, since an opponent may also stand still because it has
+  /// nothing to do; the hole may be an old one, as a vein is mined by coming back to the same cells.
+  /// If they dig a cell we know has ore, they probably have a radar around; the burials around get
+  /// more likely to be radars.
+  fn record_opponent_stop(&mut self, x: i32, y: i32) {
+    if !self.vein_cells([x, y]).any(|[vx, vy]| self.cell(vx, vy).map(|cell| cell.has_hole).unwrap_or(false)) {
+      return;
+    }
+
+    self.opponent_stops.push(([x, y], self.turn));
+
+    let precise = self.vein_cells([x, y]).any(|[vx, vy]| self.known_ore(vx, vy).unwrap_or(0) > 0);
+
+    if precise {
+      let turn = self.turn;
+
+      for suspect in &mut self.burial_suspects {
+        if suspect.turn < turn && manh_dist(suspect.cell, [x, y]) <= 5 {
+          suspect.radar_odds *= RADAR_EVIDENCE_FACTOR;
         }
-      } else {
-        match entity_type {
-          EntityType::Miner | EntityType::OpponentMiner => {
-            if x == -1 && y == -1 {
-              // this miner is dead
-              game_state.kill(uid);
-            }
+      }
+    }
+  }
 
-            // update position
-            game_state.update_position(uid, x, y);
+  /// Record an opponent burying something around its position.
+  ///
+  /// Burying digs a hole, so if a hole just appeared next to the opponent, we know exactly where
+  /// the item is.
+  ///
+  /// The place also tells us what the item probably is: traps are burried on known ore, radars in
+  /// places nobody knows anything about.
+  fn record_burial(&mut self, x: i32, y: i32) {
+    let hole = self.grid.new_holes().iter().copied().find(|&hole| manh_dist(hole, [x, y]) <= 1);
+    let cells = self.vein_cells([x, y]).collect::<Vec<_>>();
+
+    let next_to_ore = cells.iter().any(|&[cx, cy]| self.known_ore(cx, cy).unwrap_or(0) > 0);
+    let unknown = cells.iter().all(|&[cx, cy]| {
+      self.known_ore(cx, cy).is_none() && !self.grid.snapshot(cx, cy).map(|cell| cell.has_hole).unwrap_or(false)
+    });
 
-            // update item
-            game_state.update_item(uid, item);
-          }
+    let radar_odds = if next_to_ore {
+      RADAR_PRIOR_ODDS * ORE_BURIAL_RADAR_FACTOR
+    } else if unknown {
+      RADAR_PRIOR_ODDS * UNKNOWN_BURIAL_RADAR_FACTOR
+    } else {
+      RADAR_PRIOR_ODDS
+    };
 
-          EntityType::BurriedRadar => {
-            // position of this radar has changed
-            game_state.update_radar_position(uid, x, y);
-          }
+    self.burial_suspects.push(BurialSuspect {
+      cell: hole.unwrap_or([x, y]),
+      exact: hole.is_some(),
+      turn: self.turn,
+      radar_odds,
+      waits: 0,
+    });
+  }
+
+  /// Forget about the burials we have dug ourselves; whatever was there is gone.
+  fn forget_dug_burials(&mut self) {
+    let dug = self.miners().filter_map(|miner| miner.dug.or(miner.burried)).collect::<Vec<_>>();
+    self.burial_suspects.retain(|suspect| !(suspect.exact && dug.contains(&suspect.cell)));
+  }
+
+  /// Burials we think are radars, along with their probability.
+  fn suspected_enemy_radars(&self) -> impl Iterator<Item = (&BurialSuspect, f32)> {
+    let radar_likely = self.params.radar_likely;
+
+    self.burial_suspects.iter()
+      .map(|suspect| (suspect, suspect.radar_likelihood()))
+      .filter(move |&(_, likelihood)| likelihood >= radar_likely)
+  }
+
+  /// Whether the opponent likely knows the ore of a cell through one of their radars.
+  fn likely_known_by_opponent(&self, x: i32, y: i32) -> bool {
+    self.suspected_enemy_radars().any(|(suspect, _)| manh_dist(suspect.cell, [x, y]) <= 4)
+  }
+
+  /// Value of digging a suspected burial.
+  ///
+  /// If it’s a radar, we deny the opponent the ore it reveals; if it’s a trap, we lose the miner
+  /// digging it, i.e. the ore it would have brought back until the end of the game.
+  fn destruction_value(&self, suspect: &BurialSuspect) -> f32 {
+    let radar = suspect.radar_likelihood();
+    let [x, y] = suspect.cell;
+    let mut intel = 0.;
+
+    for rx in x - 4 ..= x + 4 {
+      for ry in y - 4 ..= y + 4 {
+        if rx > 0 && self.cell(rx, ry).is_some() && manh_dist([x, y], [rx, ry]) <= 4 {
+          intel += self.known_ore(rx, ry).map(|ore| ore as f32).unwrap_or(UNKNOWN_CELL_ORE_GUESS);
+        }
+      }
+    }
+
+    let miner_value = self.remaining_turns() as f32 / ORE_ROUND_TRIP_TURNS;
+
+    radar * intel - (1. - radar) * miner_value
+  }
+
+  /// Whether one of our miners, other than the given one, would be caught in an explosion at a
+  /// given cell, either where it stands or where its order takes it this turn.
+  fn other_miner_adjacent(&self, miner_index: usize, cell: [i32; 2]) -> bool {
+    self.miners().enumerate().any(|(index, miner)| {
+      let [dx, dy] = miner.order.destination();
+      let next = end_position([miner.x, miner.y], Request::Move(dx, dy));
+
+      index != miner_index && miner.alive && (manh_dist([miner.x, miner.y], cell) <= 1 || manh_dist(next, cell) <= 1)
+    })
+  }
+
+  /// Suspected burials worth destroying nobody is taking care of yet.
+  fn destruction_targets(&self) -> Vec<[i32; 2]> {
+    self.burial_suspects.iter()
+      .filter(|suspect| suspect.exact && suspect.waits < BURIAL_DESTROY_MAX_WAITS && self.destruction_value(suspect) > 0.)
+      .map(|suspect| suspect.cell)
+      .filter(|&[x, y]| !self.miners().any(|miner| matches!(miner.order, Order::DestroyAt(dx, dy, ..) if [dx, dy] == [x, y])))
+      .collect()
+  }
+
+  /// Closest miner with a given role able to go destroy a burial.
+  fn closest_destroyer(&self, role: Role, cell: [i32; 2]) -> Option<usize> {
+    self.miners().enumerate()
+      .filter(|(_, miner)| miner.alive && miner.role == role && miner.item.is_none() && matches!(miner.order, Order::GoTo(..) | Order::DigAt(..)))
+      .map(|(miner_index, miner)| (miner_index, turns_to_reach([miner.x, miner.y], cell)))
+      .filter(|&(_, turns)| turns <= BURIAL_DESTROY_MAX_TURNS)
+      .min_by_key(|&(_, turns)| turns)
+      .map(|(miner_index, _)| miner_index)
+  }
+
+  /// Send guards close enough to dig the suspected burials worth destroying.
+  fn assign_burial_destructions(&mut self) {
+    for [x, y] in self.destruction_targets() {
+      let closest = self.closest_destroyer(Role::Guard, [x, y]);
 
-          EntityType::BurriedTrap => {
-            // position of this trap has changed
-            game_state.update_trap_position(uid, x, y);
+      if let Some(miner_index) = closest {
+        let miner = &self.miners[miner_index];
+        let from = self.dig_spot([miner.x, miner.y], x, y);
+
+        let uid = miner.uid;
+        self.log(Some(uid), EventKind::Destroying([x, y]));
+        self.miners[miner_index].order = Order::DestroyAt(x, y, from[0], from[1]);
+      }
+    }
+  }
+
+  /// Cells an opponent stopping at a given place can dig.
+  fn vein_cells(&self, stop: [i32; 2]) -> impl Iterator<Item = [i32; 2]> + '_ {
+    [[0, 0], [-1, 0], [1, 0], [0, -1], [0, 1]].iter()
+      .map(move |[dx, dy]| [stop[0] + dx, stop[1] + dy])
+      .filter(move |&[x, y]| x > 0 && self.cell(x, y).is_some())
+  }
+
+  /// Detect the veins opponents are actively mining and decide what to do about them.
+  ///
+  /// A vein is contested when an opponent stopped several times at the same place recently. We
+  /// then estimate how much ore will be left when our closest miner gets there, given the rate at
+  /// which they mine it: if there’s some left, we race them; otherwise, we trap it.
+  fn update_contested_veins(&mut self) {
+    let turn = self.turn;
+    self.opponent_stops.retain(|&(_, stop_turn)| stop_turn + CONTESTED_WINDOW > turn);
+
+    let mut stops = HashMap::<[i32; 2], usize>::new();
+    for &(stop, _) in &self.opponent_stops {
+      *stops.entry(stop).or_insert(0) += 1;
+    }
+
+    let mut veins = HashMap::new();
+
+    for (stop, count) in stops {
+      if count < CONTESTED_MIN_STOPS {
+        continue;
+      }
+
+      let safe_cells = self.vein_cells(stop).filter(|&[x, y]| !self.is_cell_dangerous(x, y)).collect::<Vec<_>>();
+      let ore_left = safe_cells.iter().filter_map(|&[x, y]| self.known_ore(x, y)).fold(None, |sum: Option<usize>, ore| {
+        Some(sum.unwrap_or(0) + ore)
+      });
+
+      let eta = self.miners()
+        .filter(|miner| miner.alive && miner.order.deployed_item().is_none())
+        .map(|miner| turns_to_reach([miner.x, miner.y], stop))
+        .min();
+
+      let decision = match (ore_left, eta) {
+        // nothing left for anyone
+        (Some(0), _) => continue,
+
+        // they know something we don’t; go check
+        (None, Some(_)) => VeinDecision::Race,
+
+        (Some(ore_left), Some(eta)) => {
+          // each stop is roughly a unit of ore taken
+          let rate = count as f32 / CONTESTED_WINDOW as f32;
+          let taken = rate * eta as f32;
+
+          if ore_left as f32 - taken >= 1. {
+            VeinDecision::Race
+          } else {
+            VeinDecision::Trap
           }
         }
+
+        (_, None) => VeinDecision::Trap,
+      };
+
+      if self.contested_veins.get(&stop) != Some(&decision) {
+        self.log(None, EventKind::VeinContested { stop, stops: count, ore_left, decision });
+      }
+
+      veins.insert(stop, decision);
+    }
+
+    self.contested_veins = veins;
+  }
+
+  /// Contested vein a cell belongs to, if any.
+  fn contested_vein(&self, x: i32, y: i32) -> Option<([i32; 2], VeinDecision)> {
+    self.contested_veins.iter()
+      .find(|(&stop, _)| manh_dist(stop, [x, y]) <= 1)
+      .map(|(&stop, &decision)| (stop, decision))
+  }
+
+  /// Whether we are racing an opponent for a given cell.
+  fn is_racing(&self, x: i32, y: i32) -> bool {
+    self.contested_vein(x, y).map(|(_, decision)| decision == VeinDecision::Race).unwrap_or(false)
+  }
+
+  /// Find where to trap a contested vein: the cell we know has the most ore.
+  fn find_vein_trap_spot(&self, stop: [i32; 2]) -> Option<[i32; 2]> {
+    self.vein_cells(stop)
+      .filter(|&[x, y]| !self.is_cell_dangerous(x, y) && !self.burried_radars.values().any(|&radar| radar == [x, y]))
+      .filter(|&[x, y]| !self.miners().any(|miner| miner.order == Order::DeployTrapAt(x, y)))
+      .max_by_key(|&[x, y]| self.known_ore(x, y).unwrap_or(0))
+  }
+
+  /// Explain what a miner is doing about contested veins, if anything.
+  fn vein_comment(&self, miner_index: usize) -> Option<String> {
+    let miner = &self.miners[miner_index];
+
+    let (cell, decision) = match miner.order {
+      Order::DigAt(x, y, ..) => ([x, y], self.contested_vein(x, y)?.1),
+      Order::DeployTrapAt(x, y) => ([x, y], self.contested_vein(x, y)?.1),
+      _ => return None
+    };
+
+    Some(format!("{} ({}, {})", decision, cell[0], cell[1]))
+  }
+
+  /// Number of dangerous cells a trap chain could blow a cell up from: the cell itself and its
+  /// neighbors.
+  fn danger_score(&self, [x, y]: [i32; 2]) -> usize {
+    [[0, 0], [-1, 0], [1, 0], [0, -1], [0, 1]].iter().filter(|[dx, dy]| self.is_cell_dangerous(x + dx, y + dy)).count()
+  }
+
+  /// Compact explanation of what a miner is up to with a given order: its role and order symbols
+  /// (see Role::symbol and Order::symbol), the target cell, the ore we expect there and its danger
+  /// score, e.g. `Pd 12,4 o1.5 d0`.
+  fn miner_comment(&self, miner_index: usize, order: Order) -> String {
+    let [x, y] = order.target();
+
+    format!("{}{} {},{} o{:.1} d{}", self.miners[miner_index].role.symbol(), order.symbol(), x, y, self.expected_ore(x, y), self.danger_score([x, y]))
+  }
+
+  /// Blow a trap up if the miner can and it would kill more opponents than our own miners, the
+  /// digger included, or as many in the denial mode.
+  ///
+  /// Our own traps are only used that way in the modes using traps; the opponents’ traps we’re
+  /// pretty sure about can be triggered at any time.
+  fn kamikaze(&self, miner_index: usize) -> Option<Request> {
+    let miner = &self.miners[miner_index];
+
+    // digging while holding an item would burry it
+    if !miner.alive || matches!(miner.item, Some(Item::Radar) | Some(Item::Trap)) {
+      return None;
+    }
+
+    let ours = self.burried_traps.values().copied().filter(|_| self.mode.uses_traps());
+    let theirs = self.burial_suspects.iter()
+      .filter(|suspect| suspect.exact && suspect.radar_likelihood() < 0.5)
+      .map(|suspect| suspect.cell);
+    let traps = ours.chain(theirs).collect::<Vec<_>>();
+
+    // the digging miner is one of the victims; when the ore is scarce, an even trade is worth it
+    let even_trade = self.mode == Mode::Denial;
+
+    traps.iter().find(|&&trap| {
+      let victims = |miners: &[Miner]| {
+        miners.iter().filter(|m| m.alive && manh_dist([m.x, m.y], trap) <= 1).count()
+      };
+      let (theirs, ours) = (victims(&self.opponent_miners), victims(&self.miners));
+
+      is_cell_at_range(trap, [miner.x, miner.y]) && (theirs > ours || even_trade && theirs == ours)
+    }).map(|&[x, y]| Request::Dig(x, y))
+  }
+
+  /// Tag dangerous cells safe if we’re sure they’re not dangerous anymore.
+  fn retag_safe_cells(&mut self) {
+    let grid = &self.grid;
+    let mut safe_cells = Vec::new();
+
+    self.dangerous_cells.retain(|&[dx, dy], dangerous_cell| {
+      // a cell is dangerous only if a radar hasn’t seen the amount of ore decrease since it was
+      // marked dangerous; stale and inferred values cannot tell us anything
+      let cell = grid.snapshot(dx, dy).unwrap();
+      let fresher = cell.seen_at > dangerous_cell.seen_at && cell.source == Some(OreSource::Radar);
+      let decreased = match (cell.ore_amount, dangerous_cell.ore_amount) {
+        (Some(ore_amount), Some(dangerous_ore_amount)) => ore_amount < dangerous_ore_amount,
+        _ => false
+      };
+
+      let r = !(fresher && decreased);
+      if !r {
+        safe_cells.push([dx, dy]);
       }
+      r
+    });
+
+    for cell in safe_cells {
+      self.log(None, EventKind::Safe(cell));
+    }
+  }
+
+  /// Cells we suspect to be trapped, i.e. the ones that could blow up next to our miners.
+  fn suspected_traps(&self) -> HashSet<[i32; 2]> {
+    self.dangerous_cells.keys().copied()
+      .chain(self.burried_traps.values().copied())
+      .chain(self.burial_suspects.iter().filter(|suspect| suspect.exact).map(|suspect| suspect.cell))
+      .collect()
+  }
+
+  /// Distance left to travel for a miner moving to a given target, when standing on a cell.
+  ///
+  /// Any cell of HQ is as good as another one to deliver ore, and any cell at range of the cell
+  /// we’re about to dig is as good as the chosen dig spot.
+  fn remaining_distance(&self, miner_index: usize, target: [i32; 2], position: [i32; 2]) -> i32 {
+    let order = self.miners[miner_index].order;
+
+    match order.dig_cell() {
+      _ if target[0] == 0 && matches!(order, Order::Deliver(..)) => position[0],
+      Some(cell) if manh_dist(target, cell) <= 1 => (manh_dist(position, cell) - 1).max(0),
+      _ => manh_dist(position, target)
     }
+  }
 
-    game_state.retag_safe_cells();
+  /// Next cell to move to in order to reach a destination, ending as few turns as possible next to
+  /// a suspected trap.
+  ///
+  /// Each turn is a step of at most MINER_SPEED cells and ending one next to a suspected trap costs
+  /// DANGER_EXPOSURE_TURNS extra turns; the destination itself is never penalized, since we’re
+  /// going there anyway. Among equivalent paths, the ones getting closer to the destination first
+  /// are preferred. If no path is better than the one the referee takes, the destination itself is
+  /// returned.
+  fn next_waypoint(&self, from: [i32; 2], to: [i32; 2], traps: &HashSet<[i32; 2]>) -> [i32; 2] {
+    if traps.is_empty() || manh_dist(from, to) <= MINER_SPEED
+      || self.cell(from[0], from[1]).is_none() || self.cell(to[0], to[1]).is_none() {
+      return to;
+    }
 
-    // try to detect if an opponent is asking for an item
-    for miner_index in 0 .. game_state.opponent_miners.len() {
-      let miner = game_state.opponent_miners[miner_index].clone();
+    let size = self.width * self.height;
+    let index = |[x, y]: [i32; 2]| y as usize * self.width + x as usize;
 
-      if miner.x == -1 && miner.y == -1 {
-        if game_state.miner_with_radar == Some(miner_index) {
-          game_state.miner_with_radar = None;
+    let mut exposed = vec![false; size];
+    for &[tx, ty] in traps {
+      for &[dx, dy] in &[[0, 0], [-1, 0], [1, 0], [0, -1], [0, 1]] {
+        if self.cell(tx + dx, ty + dy).is_some() {
+          exposed[index([tx + dx, ty + dy])] = true;
         }
+      }
+    }
+
+    // the referee moves horizontally first
+    let penalty = |cell: [i32; 2]| if cell != to && exposed[index(cell)] { self.params.danger_exposure_turns } else { 0 };
+    let mut direct_cost = 0;
+    let mut position = from;
+
+    while position != to {
+      position = end_position(position, Request::Move(to[0], to[1]));
+      direct_cost += 1 + penalty(position);
+    }
+
+    let mut costs = vec![(u32::MAX, i32::MAX); size];
+    let mut first_steps = vec![None; size];
+    let mut heap = BinaryHeap::new();
 
+    costs[index(from)] = (0, manh_dist(from, to));
+    heap.push(Reverse(((0, manh_dist(from, to)), from)));
+
+    while let Some(Reverse((cost, cell))) = heap.pop() {
+      if cell == to {
+        return if cost.0 < direct_cost { first_steps[index(to)].unwrap_or(to) } else { to };
+      }
+
+      if cost > costs[index(cell)] {
         continue;
       }
 
-      // we don’t do anything at the first round since velocity gradients are not yet computed
-      if turn != 0 {
-        if miner.velocity_gradient() == [0, 0] {
-          if miner.x == 0 {
-            eprintln!("{} -> might be asking for an item!", miner_index);
-            game_state.dangerous_opponents.insert(miner_index);
-          } else if game_state.dangerous_opponents.contains(&miner_index) {
-            game_state.dangerous_opponents.remove(&miner_index);
-
-            eprintln!("{} -> might be burying a radar or a trap around ({}, {})!", miner_index, miner.x, miner.y);
-
-            // maybe the trap is on the current player position
-            let cell = game_state.cell(miner.x, miner.y).unwrap().clone();
-            eprintln!("({}, {}) is dangerous", miner.x, miner.y);
-            game_state.dangerous_cells.insert([miner.x, miner.y], cell);
-
-            // we add the whole cross as dangerous too, which is very “defensive” but whatever
-            for i in &[-1, 1] {
-              if let Some(cell) = game_state.cell(miner.x + i, miner.y).cloned() {
-                eprintln!("({}, {}) is dangerous", miner.x + i, miner.y);
-                game_state.dangerous_cells.insert([miner.x + i, miner.y], cell);
-              }
-
-              if let Some(cell) = game_state.cell(miner.x, miner.y + i).cloned() {
-                eprintln!("({}, {}) is dangerous", miner.x, miner.y + i);
-                game_state.dangerous_cells.insert([miner.x, miner.y + i], cell);
-              }
-            }
+      for dx in -MINER_SPEED ..= MINER_SPEED {
+        let span = MINER_SPEED - dx.abs();
+
+        for dy in -span ..= span {
+          let next = [cell[0] + dx, cell[1] + dy];
+
+          if next == cell || self.cell(next[0], next[1]).is_none() {
+            continue;
+          }
+
+          let next_cost = (cost.0 + 1 + penalty(next), manh_dist(next, to));
+
+          if next_cost < costs[index(next)] {
+            costs[index(next)] = next_cost;
+            first_steps[index(next)] = first_steps[index(cell)].or(Some(next));
+            heap.push(Reverse((next_cost, next)));
           }
         }
       }
     }
 
-    // select a miner to carry radar if not already there
-    if game_state.radar_cooldown == 0
-      && game_state.miner_with_radar.is_none()
-      && game_state.burried_radars.len() < 10
-      && game_state.visible_ore_amount() < MIN_ORE_NO_RADAR_NEEDED
-    {
-      game_state.assign_radar();
+    to
+  }
+
+  /// Replace the destination of every move with the next waypoint of a safe path to it.
+  fn plan_paths(&self, requests: &mut [RequestComment]) {
+    let traps = self.suspected_traps();
+
+    for (miner, request) in self.miners.iter().zip(requests) {
+      if let Request::Move(x, y) = request.req {
+        let [wx, wy] = self.next_waypoint([miner.x, miner.y], [x, y], &traps);
+        request.req = Request::Move(wx, wy);
+      }
     }
+  }
 
-    // update the exploration distances if needed
-    game_state.update_exploration_distances();
+  /// Keep our miners from ending their turn next to the same suspected trap.
+  ///
+  /// Miners not moving keep their requests; moving ones are re-routed, in order, to a cell as
+  /// close to their destination as the one they were heading to, if any.
+  fn spread_miners(&mut self, requests: &mut [RequestComment]) {
+    let traps = self.suspected_traps();
+    let mut claimed = HashSet::new();
+    let adjacent_traps = |position: [i32; 2]| {
+      traps.iter().copied().filter(move |&trap| manh_dist(trap, position) <= 1)
+    };
 
-    for miner_index in 0 .. game_state.miners.len() {
-      let miner = game_state.miners[miner_index].clone();
-
-      let request = if Some(miner_index) == game_state.miner_with_radar {
-        if let Order::DeployRadarAt(x, y) = miner.order {
-          if miner.item == Some(Item::Radar) {
-            // if that unit has already the radar
-            if is_cell_at_range([x, y], [miner.x, miner.y]) {
-              // if we arrived at destination, just burry the radar
-              game_state.miner_with_radar = None;
-              game_state.miners[miner_index].order = game_state.choose_order(miner_index);
-              Request::Dig(x, y) // FIXME: UNSAFE IF TRAP
-            } else {
-              // otherwise, go there
-              Request::Move(x, y)
-            }
-          } else if miner.x != 0 {
-            // go home to ask for a radar
-            Request::back_to_hq([miner.x, miner.y])
-          } else {
-            // ask a radar
-            Request::Item(RequestItem::Radar)
+    let mut movers = Vec::new();
+
+    for miner_index in (0 .. requests.len()).filter(|&miner_index| self.miners[miner_index].alive) {
+      let miner = &self.miners[miner_index];
+
+      match requests[miner_index].req {
+        Request::Move(x, y) => movers.push((miner_index, [x, y])),
+        _ => claimed.extend(adjacent_traps([miner.x, miner.y])),
+      }
+    }
+
+    for (miner_index, target) in movers {
+      let miner = self.miners[miner_index].clone();
+      let from = [miner.x, miner.y];
+      let end = end_position(from, requests[miner_index].req);
+
+      if adjacent_traps(end).all(|trap| !claimed.contains(&trap)) {
+        claimed.extend(adjacent_traps(end));
+        continue;
+      }
+
+      let cost = self.remaining_distance(miner_index, target, end);
+      let mut best = None;
+
+      for x in from[0] - MINER_SPEED ..= from[0] + MINER_SPEED {
+        for y in from[1] - MINER_SPEED ..= from[1] + MINER_SPEED {
+          let candidate = [x, y];
+
+          if manh_dist(from, candidate) > MINER_SPEED
+            || self.cell(x, y).is_none()
+            || traps.contains(&candidate)
+            || adjacent_traps(candidate).any(|trap| claimed.contains(&trap)) {
+            continue;
           }
-        } else {
-          unreachable!()
-        }
-      } else {
-        match miner.order {
-          Order::GoTo(x, y) => {
-            game_state.order_go_to(miner_index, x, y, x, y)
+
+          let candidate_cost = self.remaining_distance(miner_index, target, candidate);
+
+          if candidate_cost > cost {
+            continue;
           }
 
-          Order::DigAt(dig_x, dig_y, x, y) => {
-            game_state.order_go_to(miner_index, x, y, dig_x, dig_y)
+          let key = (candidate_cost, manh_dist(candidate, end));
+          if best.map(|(_, best_key)| key < best_key).unwrap_or(true) {
+            best = Some((candidate, key));
           }
+        }
+      }
 
-          Order::Deliver(..) => {
-            if miner.x != 0 {
-              Request::back_to_hq([miner.x, miner.y])
-            } else {
-              let order = game_state.choose_order(miner_index);
-              let [dx, dy] = order.destination();
-              game_state.miners[miner_index].order = order;
+      let position = match best {
+        Some((candidate, (candidate_cost, _))) => {
+          self.log(Some(miner.uid), EventKind::Spreading { from: end, to: candidate });
 
-              Request::Move(dx, dy)
-            }
+          // digging from there is as good as from the planned spot
+          if candidate_cost == 0 && miner.order.dig_cell().is_some() {
+            self.miners[miner_index].order = miner.order.with_destination(candidate[0], candidate[1]);
           }
 
-          Order::Stay(..) => Request::Wait,
-
-          _ => unreachable!()
+          requests[miner_index].req = Request::Move(candidate[0], candidate[1]);
+          candidate
         }
+
+        None => end
       };
 
-      request.submit();
+      claimed.extend(adjacent_traps(position));
     }
+  }
 
-    game_state.setup_next_turn();
-    turn += 1;
+  /// Guess what the opponents are doing from their moves.
+  ///
+  /// An opponent waiting at HQ might be asking for an item; if it stops again outside of HQ, it’s
+  /// probably burrying it. Otherwise, stopping outside of HQ means digging.
+  fn observe_opponents(&mut self) {
+    for miner_index in 0 .. self.opponent_miners.len() {
+      let miner = self.opponent_miners[miner_index].clone();
+
+      // we don’t do anything at the first round since velocity gradients are not yet computed
+      if !miner.alive || self.turn == 0 || miner.velocity_gradient() != [0, 0] {
+        continue;
+      }
+
+      if miner.x == 0 {
+        self.log(Some(miner.uid), EventKind::RequestSuspected);
+        self.dangerous_opponents.insert(miner_index);
+      } else if self.dangerous_opponents.remove(&miner_index) {
+        self.log(Some(miner.uid), EventKind::BurialSuspected([miner.x, miner.y]));
+
+        self.record_burial(miner.x, miner.y);
+
+        // maybe the trap is on the current player position
+        self.mark_dangerous(miner.x, miner.y);
+
+        // we add the whole cross as dangerous too, which is very “defensive” but whatever
+        for i in &[-1, 1] {
+          self.mark_dangerous(miner.x + i, miner.y);
+          self.mark_dangerous(miner.x, miner.y + i);
+        }
+      } else {
+        self.record_opponent_stop(miner.x, miner.y);
+      }
+    }
+  }
+
+  /// Mark a cell as dangerous, remembering what we knew about it at that moment.
+  fn mark_dangerous(&mut self, x: i32, y: i32) {
+    if let Some(cell) = self.known_cell(x, y).copied() {
+      self.log(None, EventKind::Dangerous([x, y]));
+      self.dangerous_cells.insert([x, y], cell);
+    }
+  }
+
+  /// Render the map as we see it, one line per row, a space and three characters per cell.
+  ///
+  /// Cells are shown as known (see the Display implementation of Cell), their first character
+  /// replaced by `R`/`T` for our radars/traps and by `!` for dangerous cells. Our live miners show
+  /// up as their index and order symbol (see Order::symbol), opponents as `E` and their count, a
+  /// `+` meaning several miners share the cell. The exploration window is marked under the column
+  /// numbers.
+  fn grid_dump(&self) -> String {
+    let mut dump = format!("turn {} ({}) {} - {}\n   ", self.turn, self.mode, self.my_score, self.opponent_score);
+    let window = self.exploration_distance .. self.exploration_distance + self.params.exploration_delta_distance;
+
+    for x in 0 .. self.width as i32 {
+      dump += &format!("{:4}", x);
+    }
+
+    dump += "\n   ";
+
+    for x in 0 .. self.width as i32 {
+      dump += if window.contains(&x) { " ~~~" } else { "    " };
+    }
+
+    dump += "\n";
+
+    for y in 0 .. self.height as i32 {
+      dump += &format!("{:2} ", y);
+
+      for x in 0 .. self.width as i32 {
+        let miners = self.miners().enumerate().filter(|(_, miner)| miner.alive && [miner.x, miner.y] == [x, y]).collect::<Vec<_>>();
+        let opponents = self.opponent_miners.iter().filter(|miner| miner.alive && [miner.x, miner.y] == [x, y]).count();
+
+        let cell = if let Some(&(index, miner)) = miners.first() {
+          let more = if miners.len() + opponents > 1 { '+' } else { ' ' };
+          format!("{}{}{}", index, miner.order.symbol(), more)
+        } else if opponents > 0 {
+          format!("E{:<2}", opponents)
+        } else {
+          let cell = self.known_cell(x, y).map(Cell::to_string).unwrap_or_default();
+          let marker = if self.burried_radars.values().any(|&radar| radar == [x, y]) {
+            Some('R')
+          } else if self.trap_cells.contains(&[x, y]) {
+            Some('T')
+          } else if self.is_cell_dangerous(x, y) {
+            Some('!')
+          } else {
+            None
+          };
+
+          match marker {
+            Some(marker) => format!("{}{}", marker, cell.get(1 ..).unwrap_or_default()),
+            None => cell,
+          }
+        };
+
+        dump += &format!(" {}", cell);
+      }
+
+      dump += "\n";
+    }
+
+    dump
+  }
+}
+
+/// Describe a single cell on the grid.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+struct Cell {
+  ore_amount: Option<usize>,
+  has_hole: bool,
+  seen_at: Option<u32>, // turn at which ore_amount was observed
+  source: Option<OreSource>, // where ore_amount comes from
+  removed: usize, // units of ore we removed since ore_amount was observed
+}
+
+impl Cell {
+  /// Whether the ore amount of this cell has been observed this turn.
+  fn is_live(&self, turn: u32) -> bool {
+    self.seen_at == Some(turn)
+  }
+
+  /// Amount of ore left, taking into account what we removed since the observation.
+  fn remaining_ore(&self) -> Option<usize> {
+    self.ore_amount.map(|ore_amount| ore_amount.saturating_sub(self.removed))
+  }
+
+  /// Confidence we have in the ore amount, between 0 (no idea) and 1 (certain).
+  fn ore_confidence(&self, turn: u32) -> f32 {
+    match (self.seen_at, self.source) {
+      (Some(seen_at), Some(source)) => {
+        source.staleness().map(|staleness| {
+          let age = turn.saturating_sub(seen_at);
+          (1. - age as f32 / staleness as f32).max(0.)
+        }).unwrap_or(1.)
+      }
+
+      _ => 0.
+    }
+  }
+
+  /// Amount of ore we expect to find, discounted by the staleness of the information.
+  fn expected_ore(&self, turn: u32) -> f32 {
+    self.remaining_ore().unwrap_or(0) as f32 * self.ore_confidence(turn)
+  }
+
+  /// Record a new ore observation.
+  fn observe(&mut self, turn: u32, ore_amount: Option<usize>, source: OreSource) {
+    self.ore_amount = ore_amount;
+    self.seen_at = Some(turn);
+    self.source = Some(source);
+    self.removed = 0;
+  }
+}
+
+/// Where an ore amount comes from.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum OreSource {
+  /// Seen by one of our radars.
+  Radar,
+  /// Seen by digging the cell ourselves.
+  Dig,
+  /// Deduced from the opponents’ behavior.
+  Inference,
+}
+
+impl OreSource {
+  /// Number of turns after which the information is not trusted anymore, if it can become stale.
+  fn staleness(self) -> Option<u32> {
+    match self {
+      OreSource::Radar => Some(RADAR_ORE_STALENESS),
+      // nobody puts ore back in a cell, so an empty cell stays empty
+      OreSource::Dig => None,
+      OreSource::Inference => Some(INFERRED_ORE_STALENESS),
+    }
+  }
+}
+
+impl fmt::Display for Cell {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let hole = if self.has_hole { 'o' } else { 'x' };
+    let ore_amount = if let Some(ore_amount) = self.ore_amount {
+      format!("{:2}", ore_amount)
+    } else {
+      "  ".to_owned()
+    };
+
+    write!(f, "{}{}", hole, ore_amount)?;
+
+    Ok(())
+  }
+}
+
+/// Everything we know about the grid.
+///
+/// The grid is made of two layers:
+///
+/// - The live layer is exactly what the referee told us this turn: ore amounts are only available
+///   under our radars.
+/// - The snapshot layer accumulates everything we have ever learnt about the cells.
+///
+/// Observations are merged into the snapshot with the following rules:
+///
+/// - A radar observation always wins: it overwrites the snapshot value and resets what we
+///   removed.
+/// - Holes never disappear: once a cell has a hole, it keeps it in the snapshot.
+/// - Our own digs and inferences are only taken into account outside of radars, since the radar
+///   already gives the actual value. A dig that brings ore back counts as a removed unit, an empty
+///   dig means the cell has no ore left. An inferred dig from an opponent removes a unit from the
+///   last known value.
+#[derive(Clone, Debug)]
+struct KnowledgeGrid {
+  width: usize,
+  height: usize,
+  live: Vec<Cell>,
+  snapshot: Vec<Cell>,
+  new_holes: Vec<[i32; 2]>, // holes that appeared this turn
+}
+
+impl KnowledgeGrid {
+  fn new(width: usize, height: usize) -> Self {
+    KnowledgeGrid {
+      width,
+      height,
+      live: vec![Cell::default(); width * height],
+      snapshot: vec![Cell::default(); width * height],
+      new_holes: Vec::new(),
+    }
+  }
+
+  fn index(&self, x: i32, y: i32) -> Option<usize> {
+    if x < 0 || x >= self.width as i32 || y < 0 || y >= self.height as i32 {
+      None
+    } else {
+      Some(y as usize * self.width + x as usize)
+    }
+  }
+
+  /// Cell as seen this turn.
+  fn live(&self, x: i32, y: i32) -> Option<&Cell> {
+    self.index(x, y).map(|index| &self.live[index])
+  }
+
+  /// Cell as we remember it.
+  fn snapshot(&self, x: i32, y: i32) -> Option<&Cell> {
+    self.index(x, y).map(|index| &self.snapshot[index])
+  }
+
+  /// Best information we have about a cell: the live cell if it’s under a radar, the snapshot
+  /// otherwise.
+  fn known(&self, turn: u32, x: i32, y: i32) -> Option<&Cell> {
+    let index = self.index(x, y)?;
+
+    if self.live[index].is_live(turn) {
+      Some(&self.live[index])
+    } else {
+      Some(&self.snapshot[index])
+    }
+  }
+
+  /// Merge a cell as given by the referee.
+  fn observe_cell(&mut self, turn: u32, x: i32, y: i32, ore_amount: Option<usize>, hole: bool) {
+    let index = match self.index(x, y) {
+      Some(index) => index,
+      None => return
+    };
+
+    if hole && !self.live[index].has_hole {
+      self.new_holes.push([x, y]);
+    }
+
+    let live = &mut self.live[index];
+    live.ore_amount = ore_amount;
+    live.seen_at = ore_amount.map(|_| turn);
+    live.source = ore_amount.map(|_| OreSource::Radar);
+    live.has_hole = hole;
+
+    let snapshot = &mut self.snapshot[index];
+    snapshot.has_hole |= hole;
+
+    // for the snapshot, we update only if we have a new value
+    if ore_amount.is_some() {
+      snapshot.observe(turn, ore_amount, OreSource::Radar);
+    }
+  }
+
+  /// Merge the result of one of our digs.
+  fn observe_dig(&mut self, turn: u32, x: i32, y: i32, got_ore: bool) {
+    let index = match self.index(x, y) {
+      Some(index) if !self.live[index].is_live(turn) => index,
+      // a radar already gives us the actual value
+      _ => return
+    };
+
+    let snapshot = &mut self.snapshot[index];
+    snapshot.has_hole = true;
+
+    if got_ore {
+      snapshot.removed += 1;
+    } else {
+      snapshot.observe(turn, Some(0), OreSource::Dig);
+    }
+  }
+
+  /// Merge a unit of ore we think has been taken by an opponent.
+  fn infer_ore_taken(&mut self, turn: u32, x: i32, y: i32) {
+    let index = match self.index(x, y) {
+      Some(index) if !self.live[index].is_live(turn) => index,
+      _ => return
+    };
+
+    let snapshot = &mut self.snapshot[index];
+
+    if let Some(ore_amount) = snapshot.ore_amount {
+      if ore_amount > 0 {
+        let removed = snapshot.removed;
+
+        snapshot.observe(turn, Some(ore_amount - 1), OreSource::Inference);
+        snapshot.removed = removed;
+      }
+    }
+  }
+
+  /// Holes that appeared this turn.
+  fn new_holes(&self) -> &[[i32; 2]] {
+    &self.new_holes
+  }
+
+  /// Forget about this turn’s changes.
+  fn next_turn(&mut self) {
+    self.new_holes.clear();
+  }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Miner {
+  x: i32,
+  y: i32,
+  prev_xy: Option<[i32; 2]>,
+  item: Option<Item>,
+  uid: UID,
+  alive: bool,
+  order: Order,
+  role: Role,
+  dug: Option<[i32; 2]>, // cell dug the previous turn empty handed, if any
+  burried: Option<[i32; 2]>, // cell we burried an item in the previous turn, if any
+}
+
+impl Miner {
+  /// Velocity of the miner as a gradient.
+  fn velocity_gradient(&self) -> [i32; 2] {
+    let [px, py] = self.prev_xy.unwrap_or([self.x, self.y]);
+    [self.x - px, self.y - py]
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Order {
+  /// Do just nothing.
+  Stay(i32, i32),
+  /// Move to a given unit.
+  ///
+  /// This is an exploration mode. If a better alternative is found, this unit should abort this
+  /// order.
+  GoTo(i32, i32),
+  /// Move to a cell to dig from.
+  ///
+  /// The first two i32 is the actual ore cell to dig. The last two i32 is the cell from which
+  /// we should dig (the destination).
+  DigAt(i32, i32, i32, i32),
+  DeployRadarAt(i32, i32),
+  DeployTrapAt(i32, i32),
+  /// Burry a radar to read the ore around it, then dig it back out.
+  FlashRadarAt(i32, i32),
+  /// Dig a flashed radar back out.
+  RecoverRadarAt(i32, i32),
+  /// Move to a cell to dig a suspected opponent burial from.
+  ///
+  /// The first two i32 is the burial cell. The last two i32 is the cell from which we dig.
+  DestroyAt(i32, i32, i32, i32),
+  Deliver(i32, i32),
+}
+
+impl Order {
+  /// Order of a dead miner: staying off the map, where the referee puts destroyed robots.
+  const DEAD: Order = Order::Stay(-1, -1);
+
+  /// A version of go_to_random restricted by a window
+  fn go_to_restricted_random(width: i32, height: i32, exploration_distance: i32, exploration_delta_distance: i32) -> Self {
+    let mut rng = thread_rng();
+    // the window keeps moving away once the whole map has been explored
+    let min_x = exploration_distance.min(width - 1);
+
+    Order::GoTo(
+      rng.gen_range(min_x, (min_x + exploration_delta_distance).min(width).max(min_x + 1)),
+      rng.gen_range(0, height)
+    )
+  }
+
+  fn destination(&self) -> [i32; 2] {
+    match *self {
+      Order::Stay(x, y) => [x, y],
+      Order::GoTo(x, y) => [x, y],
+      Order::DigAt(_, _, x, y) => [x, y],
+      Order::DeployRadarAt(x, y) => [x, y],
+      Order::DeployTrapAt(x, y) => [x, y],
+      Order::FlashRadarAt(x, y) => [x, y],
+      Order::RecoverRadarAt(x, y) => [x, y],
+      Order::DestroyAt(_, _, x, y) => [x, y],
+      Order::Deliver(x, y) => [x, y],
+    }
+  }
+
+  fn is_random(&self) -> bool {
+    matches!(*self, Order::GoTo(..))
+  }
+
+  fn is_digging_order(&self) -> bool {
+    matches!(*self, Order::DigAt(..))
+  }
+
+  /// Cell this order is about to dig, if any.
+  fn dig_cell(&self) -> Option<[i32; 2]> {
+    match *self {
+      Order::DigAt(x, y, ..) | Order::DestroyAt(x, y, ..) => Some([x, y]),
+      _ => None
+    }
+  }
+
+  /// Same order, going somewhere else.
+  fn with_destination(self, x: i32, y: i32) -> Self {
+    match self {
+      Order::Stay(..) => Order::Stay(x, y),
+      Order::GoTo(..) => Order::GoTo(x, y),
+      Order::DigAt(dig_x, dig_y, ..) => Order::DigAt(dig_x, dig_y, x, y),
+      Order::DeployRadarAt(..) => Order::DeployRadarAt(x, y),
+      Order::DeployTrapAt(..) => Order::DeployTrapAt(x, y),
+      Order::FlashRadarAt(..) => Order::FlashRadarAt(x, y),
+      Order::RecoverRadarAt(..) => Order::RecoverRadarAt(x, y),
+      Order::DestroyAt(cell_x, cell_y, ..) => Order::DestroyAt(cell_x, cell_y, x, y),
+      Order::Deliver(..) => Order::Deliver(x, y),
+    }
+  }
+
+  /// Role of a miner following this order.
+  fn role(&self) -> Role {
+    match self.deployed_item() {
+      Some(item) => Role::carrying(item),
+      None if matches!(*self, Order::DestroyAt(..)) => Role::Guard,
+      None => Role::Prospector,
+    }
+  }
+
+  /// Cell this order is about: the cell to dig, or else the destination.
+  fn target(&self) -> [i32; 2] {
+    self.dig_cell().unwrap_or_else(|| self.destination())
+  }
+
+  /// Single letter standing for the kind of this order, used in the grid dump and the miner
+  /// comments.
+  fn symbol(&self) -> char {
+    match *self {
+      Order::Stay(..) => 's',
+      Order::GoTo(..) => 'g',
+      Order::DigAt(..) => 'd',
+      Order::DeployRadarAt(..) => 'r',
+      Order::DeployTrapAt(..) => 't',
+      Order::FlashRadarAt(..) => 'f',
+      Order::RecoverRadarAt(..) => 'c',
+      Order::DestroyAt(..) => 'k',
+      Order::Deliver(..) => 'h',
+    }
+  }
+
+  /// Item this order is about to deploy, if any.
+  fn deployed_item(&self) -> Option<RequestItem> {
+    match *self {
+      Order::DeployRadarAt(..) | Order::FlashRadarAt(..) | Order::RecoverRadarAt(..) => Some(RequestItem::Radar),
+      Order::DeployTrapAt(..) => Some(RequestItem::Trap),
+      _ => None
+    }
+  }
+}
+
+/// A way of playing: given the current state of the game, decide what every miner does.
+trait Strategy {
+  /// Name the strategy is registered with.
+  fn name(&self) -> &'static str;
+
+  /// Requests of every miner, in order, for the current turn.
+  fn decide(&mut self, game_state: &mut GameState) -> Vec<RequestComment>;
+}
+
+/// Instantiate a strategy.
+type NewStrategy = fn() -> Box<dyn Strategy>;
+
+/// Available strategies, by name.
+const STRATEGIES: &[(&str, NewStrategy)] = &[
+  ("default", || Box::new(DefaultStrategy)),
+  ("prospect", || Box::new(ProspectStrategy)),
+  ("v04", || Box::new(V04Strategy)),
+];
+
+/// Environment variable holding the name of the strategy to play.
+const STRATEGY_VAR: &str = "UTG_STRATEGY";
+
+/// Instantiate a strategy by its name.
+fn strategy_by_name(name: &str) -> Option<Box<dyn Strategy>> {
+  STRATEGIES.iter().find(|&&(strategy, _)| strategy == name).map(|(_, new)| new())
+}
+
+/// Strategy to play, chosen with the first command line argument or the UTG_STRATEGY environment
+/// variable, the default one being played otherwise.
+fn chosen_strategy() -> Box<dyn Strategy> {
+  let name = std::env::args().nth(1).or_else(|| std::env::var(STRATEGY_VAR).ok());
+
+  match name {
+    Some(name) => strategy_by_name(&name).unwrap_or_else(|| {
+      let names = STRATEGIES.iter().map(|&(name, _)| name).collect::<Vec<_>>();
+      eprintln!("unknown strategy {}; available ones are: {}", name, names.join(", "));
+      Box::new(DefaultStrategy)
+    }),
+
+    None => Box::new(DefaultStrategy)
+  }
+}
+
+/// Carry the orders of every miner out according to its role, and travel safely.
+///
+/// Miners are allowed to blow traps up only if `kamikaze` is set.
+fn carry_orders_out(game_state: &mut GameState, kamikaze: bool) -> Vec<RequestComment> {
+  let mut requests = Vec::with_capacity(game_state.miners.len());
+
+  // a dig completes the order the miner had before acting, even if it has moved on to the next one
+  let dug_orders = game_state.miners().map(|miner| miner.order).collect::<Vec<_>>();
+
+  for miner_index in 0 .. game_state.miners.len() {
+    let miner = game_state.miners[miner_index].clone();
+    let kamikaze = if kamikaze { game_state.kamikaze(miner_index) } else { None };
+
+    let request: RequestComment = if !miner.alive {
+      Request::Wait.comment("dead")
+    } else if let Some(request) = kamikaze {
+      request.comment("kamikaze")
+    } else {
+      // each role knows how to carry its orders out
+      match miner.role {
+        Role::Prospector => prospector::act(game_state, miner_index),
+        Role::RadarRunner => radar_runner::act(game_state, miner_index),
+        Role::Trapper => trapper::act(game_state, miner_index),
+        Role::Guard => guard::act(game_state, miner_index),
+      }
+    };
+
+    requests.push(request);
+  }
+
+  // travel safely and don’t let a single trap kill several of our miners
+  game_state.plan_paths(&mut requests);
+  game_state.spread_miners(&mut requests);
+
+  requests.into_iter().enumerate().map(|(miner_index, request)| {
+    let request = match game_state.vein_comment(miner_index) {
+      Some(comment) => request.tagged(comment),
+      None => request
+    };
+
+    let request = if game_state.miners[miner_index].alive {
+      let order = match request.req {
+        Request::Dig(..) => dug_orders[miner_index],
+        _ => game_state.miners[miner_index].order
+      };
+
+      request.tagged(game_state.miner_comment(miner_index, order))
+    } else {
+      request
+    };
+
+    request.tagged(game_state.mode.to_string())
+  }).collect()
+}
+
+/// Everything we know how to do: modes, traps, contested veins, burial destructions, etc.
+///
+/// This is the v0.5 logic; the v0.4 one is played by [`V04Strategy`].
+struct DefaultStrategy;
+
+impl Strategy for DefaultStrategy {
+  fn name(&self) -> &'static str {
+    "default"
+  }
+
+  fn decide(&mut self, game_state: &mut GameState) -> Vec<RequestComment> {
+    // find out which veins the opponents are mining
+    game_state.update_contested_veins();
+
+    // at the end of the game, ore carriers go home first
+    game_state.prioritize_deliveries();
+
+    // switch strategic mode if needed and plan traps accordingly
+    game_state.update_mode();
+    game_state.plan_traps();
+
+    // decide who mines and who takes care of the items
+    game_state.allocate_roles();
+
+    // plan item pickups and assign carriers
+    game_state.schedule_items();
+
+    // get rid of the opponent burials worth it
+    game_state.assign_burial_destructions();
+
+    // update the exploration distances if needed
+    game_state.update_exploration_distances();
+
+    carry_orders_out(game_state, true)
+  }
+}
+
+/// Mine and burry radars, nothing else: no traps, no burial destructions and a balanced mode.
+struct ProspectStrategy;
+
+impl Strategy for ProspectStrategy {
+  fn name(&self) -> &'static str {
+    "prospect"
+  }
+
+  fn decide(&mut self, game_state: &mut GameState) -> Vec<RequestComment> {
+    game_state.update_contested_veins();
+    game_state.prioritize_deliveries();
+    game_state.allocate_roles();
+    game_state.schedule_items();
+    game_state.update_exploration_distances();
+
+    carry_orders_out(game_state, false)
+  }
+}
+
+/// The v0.4 logic: a single radar carrier at a time, the miner closest to HQ, everyone else mining
+/// the nearest ore; no traps, no modes, no contested veins and no burial destructions.
+///
+/// The `v04` bot of the crate is the frozen copy of the v0.4 code; this strategy replays its
+/// decisions with the current helpers, so that both versions can be compared in the same bot.
+struct V04Strategy;
+
+impl Strategy for V04Strategy {
+  fn name(&self) -> &'static str {
+    "v04"
+  }
+
+  fn decide(&mut self, game_state: &mut GameState) -> Vec<RequestComment> {
+    // select a miner to carry a radar if not already there
+    if game_state.radar_cooldown == 0
+      && game_state.carriers_count(RequestItem::Radar) == 0
+      && game_state.burried_radars.len() < game_state.params.max_radars
+      && game_state.visible_ore_amount() < game_state.params.min_ore_no_radar_needed
+    {
+      let closest = game_state.miners().enumerate()
+        .filter(|(_, miner)| miner.alive)
+        .min_by_key(|(_, miner)| miner.x)
+        .map(|(miner_index, _)| miner_index);
+
+      if let (Some(miner_index), Some([x, y])) = (closest, game_state.find_best_radar_spot()) {
+        game_state.miners[miner_index].order = Order::DeployRadarAt(x, y);
+      }
+    }
+
+    for miner in &mut game_state.miners {
+      miner.role = if miner.alive { miner.order.role() } else { Role::Prospector };
+    }
+
+    game_state.update_exploration_distances();
+
+    carry_orders_out(game_state, false)
+  }
+}
+
+/// Behaviour of the miners digging ore.
+mod prospector {
+  use super::*;
+
+  /// Follow a mining order; any other order is dropped for a mining one.
+  pub fn act(game_state: &mut GameState, miner_index: usize) -> RequestComment {
+    let miner = game_state.miners[miner_index].clone();
+
+    match miner.order {
+      Order::GoTo(x, y) => {
+        game_state.order_go_to(miner_index, x, y, x, y).into()
+      }
+
+      Order::DigAt(dig_x, dig_y, x, y) => {
+        game_state.order_go_to(miner_index, x, y, dig_x, dig_y).into()
+      }
+
+      Order::Deliver(..) if miner.x != 0 => {
+        Request::back_to_hq([miner.x, miner.y]).into()
+      }
+
+      Order::Stay(..) => Request::Wait.into(),
+
+      _ => {
+        let order = game_state.choose_order(miner_index);
+        let [dx, dy] = order.destination();
+        game_state.miners[miner_index].order = order;
+
+        Request::Move(dx, dy).into()
+      }
+    }
+  }
+}
+
+/// Behaviour of the miners burrying radars.
+mod radar_runner {
+  use super::*;
+
+  /// Deploy, flash or recover a radar, or mine while waiting for one.
+  pub fn act(game_state: &mut GameState, miner_index: usize) -> RequestComment {
+    match game_state.miners[miner_index].order {
+      Order::DeployRadarAt(x, y) | Order::FlashRadarAt(x, y) => {
+        game_state.order_deploy(miner_index, RequestItem::Radar, x, y)
+      }
+
+      Order::RecoverRadarAt(x, y) => {
+        game_state.order_recover_radar(miner_index, x, y)
+      }
+
+      _ => prospector::act(game_state, miner_index)
+    }
+  }
+}
+
+/// Behaviour of the miners burrying traps.
+mod trapper {
+  use super::*;
+
+  /// Deploy a trap, or mine while waiting for one.
+  pub fn act(game_state: &mut GameState, miner_index: usize) -> RequestComment {
+    match game_state.miners[miner_index].order {
+      Order::DeployTrapAt(x, y) => {
+        game_state.order_deploy(miner_index, RequestItem::Trap, x, y)
+      }
+
+      _ => prospector::act(game_state, miner_index)
+    }
+  }
+}
+
+/// Behaviour of the miners destroying the opponent burials.
+mod guard {
+  use super::*;
+
+  /// Destroy a suspected burial, or mine while there’s nothing to destroy.
+  pub fn act(game_state: &mut GameState, miner_index: usize) -> RequestComment {
+    match game_state.miners[miner_index].order {
+      Order::DestroyAt(x, y, from_x, from_y) => {
+        game_state.order_destroy(miner_index, x, y, from_x, from_y)
+      }
+
+      _ => prospector::act(game_state, miner_index)
+    }
+  }
+}
+
+/// Environment variable holding the path replays are written to, `-` meaning stderr.
+#[cfg(feature = "local")]
+const REPLAY_VAR: &str = "UTG_REPLAY";
+
+/// Record of a game, to be visualized or replayed offline.
+///
+/// Every line is tagged so that a replay can be picked out of a noisy stderr dump:
+///
+/// - `@<` an input line;
+/// - `@>` an output line;
+/// - `@#` an event of the turn, whatever the verbosity;
+/// - `@!` the end of a turn, along with the cells we thought dangerous, as `x,y` words.
+struct Replay {
+  out: Option<Box<dyn Write>>,
+}
+
+impl Replay {
+  /// Replay written where UTG_REPLAY says, or on stderr if REPLAY_DUMP is set.
+  #[cfg(feature = "local")]
+  fn open() -> Self {
+    let out: Option<Box<dyn Write>> = match std::env::var(REPLAY_VAR) {
+      Ok(ref path) if path == "-" => Some(Box::new(io::stderr())),
+
+      Ok(path) => match std::fs::File::create(&path) {
+        Ok(file) => Some(Box::new(io::BufWriter::new(file))),
+
+        Err(err) => {
+          eprintln!("cannot write the replay to {}: {}", path, err);
+          None
+        }
+      },
+
+      Err(_) if REPLAY_DUMP => Some(Box::new(io::stderr())),
+      Err(_) => None,
+    };
+
+    Replay { out }
+  }
+
+  /// Replay written on stderr if REPLAY_DUMP is set.
+  #[cfg(not(feature = "local"))]
+  fn open() -> Self {
+    let out: Option<Box<dyn Write>> = if REPLAY_DUMP { Some(Box::new(io::stderr())) } else { None };
+    Replay { out }
+  }
+
+  fn write(&mut self, tag: &str, line: &str) {
+    if let Some(ref mut out) = self.out {
+      let _ = writeln!(out, "{}{}", tag, line.trim_end());
+    }
+  }
+
+  fn input(&mut self, line: &str) {
+    self.write("@<", line);
+  }
+
+  fn output(&mut self, line: &str) {
+    self.write("@>", line);
+  }
+
+  /// Close the turn with what we know about it.
+  fn end_turn(&mut self, game_state: &GameState) {
+    if self.out.is_none() {
+      return;
+    }
+
+    for event in &game_state.events {
+      self.write("@#", &event.to_string());
+    }
+
+    let dangerous = game_state.dangerous_cells.keys().map(|[x, y]| format!(" {},{}", x, y)).collect::<String>();
+    self.write("@!", &dangerous);
+
+    if let Some(ref mut out) = self.out {
+      let _ = out.flush();
+    }
+  }
+}
+
+/// Read a line of the input, recording it in the replay.
+fn read_line<R>(input: &mut R, replay: &mut Replay) -> String where R: BufRead {
+  let mut input_line = String::new();
+  input.read_line(&mut input_line).unwrap();
+  replay.input(&input_line);
+  input_line
+}
+
+/// Read the initialization input: the size of the map.
+fn read_init<R>(input: &mut R, replay: &mut Replay) -> (usize, usize) where R: BufRead {
+  let input_line = read_line(input, replay);
+  let inputs = input_line.split(" ").collect::<Vec<_>>();
+
+  let width = parse_input!(inputs[0], usize);
+  let height = parse_input!(inputs[1], usize); // size of the map
+
+  (width, height)
+}
+
+/// Read the input of a turn into the game state.
+fn read_turn<R>(input: &mut R, replay: &mut Replay, game_state: &mut GameState) where R: BufRead {
+  let input_line = read_line(input, replay);
+  let inputs = input_line.split(" ").collect::<Vec<_>>();
+
+  let my_score = parse_input!(inputs[0], u32); // Amount of ore delivered
+  let opponent_score = parse_input!(inputs[1], u32);
+
+  game_state.set_my_score(my_score);
+  game_state.set_opponent_score(opponent_score);
+
+  for y in 0 .. game_state.height {
+    let input_line = read_line(input, replay);
+    let inputs = input_line.split_whitespace().collect::<Vec<_>>();
+
+    // we skip x = 0 as it’s HQ
+    for x in 1 .. game_state.width {
+      let ore: Option<usize> = inputs[2 * x].trim().parse().ok(); // amount of ore or "?" if unknown
+      let hole = parse_input!(inputs[2 * x + 1], u32) == 1; // 1 if cell has a hole
+
+      game_state.update_cell(x, y, ore, hole);
+    }
+  }
+
+  let input_line = read_line(input, replay);
+  let inputs = input_line.split(" ").collect::<Vec<_>>();
+
+  let entity_count = parse_input!(inputs[0], u32); // number of entities visible to you
+  let radar_cooldown = parse_input!(inputs[1], u32); // turns left until a new radar can be requested
+  let trap_cooldown = parse_input!(inputs[2], u32); // turns left until a new trap can be requested
+
+  game_state.set_radar_cooldown(radar_cooldown);
+  game_state.set_trap_cooldown(trap_cooldown);
+
+  for _ in 0..entity_count as usize {
+    let input_line = read_line(input, replay);
+    let inputs = input_line.split(" ").collect::<Vec<_>>();
+
+    let uid = parse_input!(inputs[0], u32); // unique id of the entity
+    let entity_type: EntityType = parse_input!(inputs[1], u32).try_into().unwrap();
+
+    let x = parse_input!(inputs[2], i32);
+    let y = parse_input!(inputs[3], i32); // position of the entity
+    let item = parse_input!(inputs[4], i32).try_into().ok(); // if this entity is a robot, the item it is carrying (-1 for NONE, 2 for RADAR, 3 for TRAP, 4 for ORE)
+
+    // check if we need to update our entities
+    if !game_state.entity_exists(uid) {
+      // if it’s a miner, add it to the list of miners
+      match entity_type {
+        EntityType::Miner => {
+          let miner_index = game_state.add_miner(Miner {
+            x,
+            y,
+            prev_xy: None,
+            item,
+            uid,
+            alive: true,
+            order: game_state.random_exploration_order(),
+            role: Role::Prospector,
+            dug: None,
+            burried: None,
+          });
+
+          game_state.add_entity(uid, Entity::Miner(miner_index));
+        }
+
+        EntityType::OpponentMiner => {
+          let opponent_miner_index = game_state.add_opponent_miner(Miner {
+            x,
+            y,
+            prev_xy: None,
+            item,
+            uid,
+            alive: true,
+            order: Order::Stay(x, y),
+            role: Role::Prospector,
+            dug: None,
+            burried: None,
+          });
+
+          game_state.add_entity(uid, Entity::OpponentMiner(opponent_miner_index));
+        }
+
+        EntityType::BurriedRadar => {
+          game_state.add_entity(uid, Entity::BurriedRadar);
+          game_state.burry_radar(uid, x, y);
+          game_state.see_item(uid);
+        }
+
+        EntityType::BurriedTrap => {
+          game_state.add_entity(uid, Entity::BurriedTrap);
+          game_state.burry_trap(uid, x, y);
+          game_state.see_item(uid);
+        }
+      }
+    } else {
+      match entity_type {
+        EntityType::Miner | EntityType::OpponentMiner => {
+          if x == -1 && y == -1 {
+            // this miner is dead
+            game_state.kill(uid);
+          }
+
+          // update position
+          game_state.update_position(uid, x, y);
+
+          // update item
+          game_state.update_item(uid, item);
+        }
+
+        EntityType::BurriedRadar => {
+          // position of this radar has changed
+          game_state.update_radar_position(uid, x, y);
+          game_state.see_item(uid);
+        }
+
+        EntityType::BurriedTrap => {
+          // position of this trap has changed
+          game_state.update_trap_position(uid, x, y);
+          game_state.see_item(uid);
+        }
+      }
+    }
+  }
+}
+
+/// Make sense of the turn just read and decide what our miners do.
+fn plan_turn(game_state: &mut GameState, strategy: &mut dyn Strategy) -> Vec<RequestComment> {
+  game_state.observe_digs();
+  game_state.observe_holes();
+  game_state.forget_dug_burials();
+  game_state.forget_unseen_items();
+  game_state.retag_safe_cells();
+
+  game_state.observe_opponents();
+
+  let requests = strategy.decide(game_state);
+
+  for (miner_index, request) in requests.iter().enumerate() {
+    let miner = &mut game_state.miners[miner_index];
+    let dug = match request.req {
+      Request::Dig(x, y) => Some([x, y]),
+      _ => None
+    };
+
+    // remember what we dig to check what we got on the next turn; digging with ore gives nothing
+    miner.dug = dug.filter(|_| miner.item.is_none());
+    miner.burried = dug.filter(|_| matches!(miner.item, Some(Item::Radar) | Some(Item::Trap)));
+  }
+
+  requests.into_iter().map(|request| request.limited(game_state.params.max_comment_length)).collect()
+}
+
+fn main() {
+  let stdin = io::stdin();
+  let mut input = stdin.lock();
+  let mut replay = Replay::open();
+  let (width, height) = read_init(&mut input, &mut replay);
+
+  let mut game_state = GameState::new(width, height, Params::load());
+  let mut strategy = chosen_strategy();
+
+  eprintln!("playing the {} strategy", strategy.name());
+
+  // game loop
+  loop {
+    read_turn(&mut input, &mut replay, &mut game_state);
+
+    let requests = plan_turn(&mut game_state, strategy.as_mut());
+
+    // the replay is complete before we answer, since the game might end right after
+    for request in &requests {
+      replay.output(&request.to_string());
+    }
+
+    replay.end_turn(&game_state);
+
+    for request in requests {
+      request.submit();
+    }
+
+    game_state.print_events();
+
+    if game_state.turn.checked_rem(game_state.params.debug_grid_every) == Some(0) {
+      eprint!("{}", game_state.grid_dump());
+    }
+
+    game_state.setup_next_turn();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use codingame_unleash_the_geek::referee::{self, Action, Game};
+  use codingame_unleash_the_geek::view::{EntityKind, PlayerView};
+  use std::io::Cursor;
+
+  /// Dig holes in the first cells of the exploration window, column after column.
+  fn dig_exploration_window(game_state: &mut GameState, holes: usize) {
+    let from = game_state.exploration_distance;
+    let to = from + game_state.params.exploration_delta_distance;
+    let height = game_state.height as i32;
+
+    for [x, y] in (from .. to).flat_map(|x| (0 .. height).map(move |y| [x, y])).take(holes) {
+      game_state.update_cell(x as usize, y as usize, None, true);
+    }
+  }
+
+  /// Game state with two miners of ours (UIDs 0 and 1) and two opponents (UIDs 2 and 3).
+  fn game_with_miners() -> GameState {
+    let mut game_state = GameState::new(30, 15, Params::default());
+
+    for uid in 0 .. 4 {
+      let miner = Miner {
+        x: 5,
+        y: 2 * uid as i32,
+        prev_xy: None,
+        item: None,
+        uid,
+        alive: true,
+        order: Order::Stay(5, 2 * uid as i32),
+        role: Role::Prospector,
+        dug: None,
+        burried: None,
+      };
+
+      if uid < 2 {
+        let index = game_state.add_miner(miner);
+        game_state.add_entity(uid, Entity::Miner(index));
+      } else {
+        let index = game_state.add_opponent_miner(miner);
+        game_state.add_entity(uid, Entity::OpponentMiner(index));
+      }
+    }
+
+    game_state
+  }
+
+  /// Kill a miner the way the input does it.
+  fn kill(game_state: &mut GameState, uid: UID) {
+    game_state.kill(uid);
+    game_state.update_position(uid, -1, -1);
+    game_state.update_item(uid, None);
+  }
+
+  /// Give the bot the input of the first player for the current turn of a game and let it plan.
+  fn drive_turn(game: &Game, game_state: &mut GameState, strategy: &mut dyn Strategy) {
+    let input = game.input(0);
+    read_turn(&mut Cursor::new(input.as_bytes()), &mut Replay { out: None }, game_state);
+    plan_turn(game_state, strategy);
+  }
+
+  /// Number of holes making the exploration window reach the exploration ratio threshold.
+  fn threshold_holes(game_state: &GameState) -> usize {
+    let cells_count = game_state.height as i32 / 2 * game_state.params.exploration_delta_distance;
+    (cells_count as f32 * game_state.params.exploration_ratio_threshold).ceil() as usize
+  }
+
+  #[test]
+  fn exploration_moves_forward_once_explored() {
+    let mut game_state = GameState::new(30, 15, Params::default());
+    let holes = threshold_holes(&game_state);
+    dig_exploration_window(&mut game_state, holes);
+
+    game_state.update_exploration_distances();
+
+    let from = game_state.params.exploration_start_min_distance;
+    let to = from + game_state.params.exploration_delta_distance;
+    assert_eq!(game_state.exploration_distance, to);
+    assert_eq!(game_state.events.iter().map(|event| event.kind).collect::<Vec<_>>(), vec![EventKind::ExplorationWindow { from, to }]);
+  }
+
+  #[test]
+  fn exploration_stays_below_threshold() {
+    let mut game_state = GameState::new(30, 15, Params::default());
+    let holes = threshold_holes(&game_state) - 1;
+    dig_exploration_window(&mut game_state, holes);
+
+    game_state.update_exploration_distances();
+
+    assert_eq!(game_state.exploration_distance, game_state.params.exploration_start_min_distance);
+    assert!(game_state.events.is_empty());
+  }
+
+  #[test]
+  fn dead_miner_waits() {
+    let mut game_state = game_with_miners();
+    kill(&mut game_state, 0);
+
+    for mut strategy in STRATEGIES.iter().map(|(_, new)| new()) {
+      let requests = strategy.decide(&mut game_state);
+
+      assert_eq!(requests[0].req, Request::Wait, "{} strategy", strategy.name());
+    }
+  }
+
+  #[test]
+  fn strategies_go_by_their_names() {
+    for &(name, _) in STRATEGIES {
+      assert_eq!(strategy_by_name(name).map(|strategy| strategy.name()), Some(name));
+    }
+  }
+
+  #[test]
+  fn dead_miner_drops_everything() {
+    let mut game_state = game_with_miners();
+    game_state.miners[0].order = Order::DeployRadarAt(10, 4);
+    game_state.miners[0].item = Some(Item::Radar);
+    game_state.miners[0].dug = Some([6, 0]);
+    game_state.miners[0].burried = Some([7, 0]);
+
+    game_state.kill(0);
+
+    let miner = &game_state.miners[0];
+    assert!(!miner.alive);
+    assert_eq!(miner.order, Order::DEAD);
+    assert_eq!(miner.item, None);
+    assert_eq!(miner.dug, None);
+    assert_eq!(miner.burried, None);
+  }
+
+  #[test]
+  fn dead_opponent_is_not_dangerous() {
+    let mut game_state = game_with_miners();
+    game_state.dangerous_opponents.insert(0);
+
+    kill(&mut game_state, 2);
+
+    assert!(!game_state.opponent_miners[0].alive);
+    assert!(!game_state.dangerous_opponents.contains(&0));
+  }
+
+  #[test]
+  fn dead_opponent_keeps_our_carriers() {
+    let mut game_state = game_with_miners();
+    game_state.miners[0].order = Order::DeployRadarAt(10, 4);
+    game_state.miners[1].order = Order::DeployTrapAt(12, 6);
+    game_state.trap_targets.push([14, 8]);
+    game_state.setup_next_turn();
+
+    // opponents have the same indices as our carriers
+    kill(&mut game_state, 2);
+    kill(&mut game_state, 3);
+    game_state.observe_opponents();
+
+    assert_eq!(game_state.miners[0].order, Order::DeployRadarAt(10, 4));
+    assert_eq!(game_state.miners[1].order, Order::DeployTrapAt(12, 6));
+    assert_eq!(game_state.trap_targets, vec![[14, 8]]);
+  }
+
+  #[test]
+  fn exploration_past_the_map_edge() {
+    for _ in 0 .. 100 {
+      let [x, y] = Order::go_to_restricted_random(30, 15, 40, EXPLORATION_DELTA_DISTANCE).destination();
+
+      assert_eq!(x, 29);
+      assert!((0 .. 15).contains(&y));
+    }
+  }
+
+  #[test]
+  fn first_turns_events() {
+    let mut game = Game::generate(7);
+    let mut game_state = GameState::new(game.width, game.height, Params::default());
+    let mut strategy = DefaultStrategy;
+
+    drive_turn(&game, &mut game_state, &mut strategy);
+    let kinds = game_state.events.iter().map(|event| event.kind).collect::<Vec<_>>();
+
+    assert!(kinds.contains(&EventKind::PickupScheduled { item: RequestItem::Radar, turns: 0 }));
+    assert!(kinds.iter().any(|kind| matches!(kind, EventKind::RoleChanged(..))));
+
+    // opponents staying in HQ might be asking for items
+    game_state.setup_next_turn();
+    game.play_turn([&[], &[]]);
+    drive_turn(&game, &mut game_state, &mut strategy);
+    let suspects = game_state.events.iter().filter(|event| event.kind == EventKind::RequestSuspected).map(|event| event.uid).collect::<Vec<_>>();
+
+    assert_eq!(suspects, (5 .. 10).map(Some).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn death_is_an_anomaly() {
+    let mut game = Game::generate(7);
+    let mut game_state = GameState::new(game.width, game.height, Params::default());
+    let mut strategy = DefaultStrategy;
+    let y = game.robots[0].y;
+
+    // our first robot burries a trap and digs it back out
+    for action in [Action::Request(referee::Item::Trap), Action::Dig(1, y), Action::Dig(1, y)] {
+      drive_turn(&game, &mut game_state, &mut strategy);
+      assert!(game_state.events.iter().all(|event| !matches!(event.kind, EventKind::Died { .. })));
+      game_state.setup_next_turn();
+      game.play_turn([&[action], &[]]);
+    }
+
+    drive_turn(&game, &mut game_state, &mut strategy);
+    let deaths = game_state.events.iter().filter(|event| matches!(event.kind, EventKind::Died { .. })).collect::<Vec<_>>();
+
+    assert_eq!(deaths.iter().map(|event| event.uid).collect::<Vec<_>>(), vec![Some(0)]);
+    assert_eq!(deaths[0].kind.level(), Level::Anomaly);
+  }
+
+  #[test]
+  fn fogged_view_reads_back() {
+    // we burry a radar and a trap while the opponent carries a radar we can’t see
+    let mut game = Game::generate(7);
+    let [y0, y1] = [game.robots[0].y, game.robots[1].y];
+    game.play_turn([&[Action::Request(referee::Item::Radar), Action::Request(referee::Item::Trap), Action::Move(4, 2)], &[Action::Request(referee::Item::Radar)]]);
+    game.play_turn([&[Action::Dig(1, y0), Action::Dig(1, y1)], &[]]);
+
+    let view = PlayerView::fog(&game, 0);
+    assert!(view.ore.iter().any(Option::is_none) && view.ore.iter().any(Option::is_some));
+    assert_eq!(game.robots[5].item, Some(referee::Item::Radar));
+    assert_eq!(view.entities.iter().find(|entity| entity.id == 5).map(|entity| entity.item), Some(None));
+
+    let input = game.init_input() + &game.input(0);
+    let mut input = Cursor::new(input.as_bytes());
+    let mut replay = Replay { out: None };
+    let (width, height) = read_init(&mut input, &mut replay);
+    let mut game_state = GameState::new(width, height, Params::default());
+    read_turn(&mut input, &mut replay, &mut game_state);
+
+    assert_eq!([game_state.my_score, game_state.opponent_score], view.scores);
+    assert_eq!([game_state.radar_cooldown, game_state.trap_cooldown], [view.radar_cooldown, view.trap_cooldown]);
+
+    for y in 0 .. height {
+      for x in 1 .. width {
+        let cell = game_state.cell(x as i32, y as i32).unwrap();
+        assert_eq!(cell.ore_amount, view.ore[y * width + x].map(|ore| ore as usize), "ore at {} {}", x, y);
+        assert_eq!(cell.has_hole, view.holes[y * width + x], "hole at {} {}", x, y);
+      }
+    }
+
+    assert_eq!(game_state.entities.len(), view.entities.len());
+
+    for entity in &view.entities {
+      let miner = match (entity.kind, game_state.entities[&entity.id]) {
+        (EntityKind::Robot, Entity::Miner(index)) => &game_state.miners[index],
+        (EntityKind::OpponentRobot, Entity::OpponentMiner(index)) => &game_state.opponent_miners[index],
+
+        (EntityKind::Radar, Entity::BurriedRadar) => {
+          assert_eq!(game_state.burried_radars[&entity.id], [entity.x, entity.y]);
+          continue;
+        }
+
+        (EntityKind::Trap, Entity::BurriedTrap) => {
+          assert!(game_state.trap_cells.contains(&[entity.x, entity.y]));
+          continue;
+        }
+
+        (kind, entity) => panic!("{:?} read as {:?}", kind, entity)
+      };
+
+      let item = entity.item.map(|item| match item {
+        referee::Item::Radar => Item::Radar,
+        referee::Item::Trap => Item::Trap,
+        referee::Item::Ore => Item::Ore,
+      });
+
+      assert_eq!([miner.x, miner.y], [entity.x, entity.y]);
+      assert_eq!(miner.item, item);
+    }
+  }
+
+  #[test]
+  fn pickups_follow_the_cooldowns() {
+    let mut game_state = game_with_miners();
+    game_state.radar_cooldown = 3;
+    game_state.trap_cooldown = 0;
+    game_state.trap_targets = vec![[10, 4], [12, 6]];
+
+    let plan = game_state.plan_pickups();
+    let turns = |item| plan.iter().filter(|pickup| pickup.item == item).map(|pickup| pickup.turn).collect::<Vec<_>>();
+
+    assert_eq!(turns(RequestItem::Radar), vec![3, 3 + ITEM_COOLDOWN]);
+    assert_eq!(turns(RequestItem::Trap), vec![0, ITEM_COOLDOWN]);
+
+    // a carrier on its way to HQ takes the first radar
+    game_state.miners[0].order = Order::DeployRadarAt(10, 4);
+    let plan = game_state.plan_pickups();
+
+    assert_eq!(plan.iter().find(|pickup| pickup.item == RequestItem::Radar).map(|pickup| pickup.turn), Some(3 + ITEM_COOLDOWN));
+  }
+
+  #[test]
+  fn no_items_wanted_in_the_endgame() {
+    let mut game_state = game_with_miners();
+    game_state.trap_targets = vec![[10, 4]];
+
+    assert!(game_state.wanted_items(RequestItem::Radar) > 0);
+    assert_eq!(game_state.wanted_items(RequestItem::Trap), 1);
+
+    game_state.turn = MAX_TURNS - ENDGAME_TURNS;
+
+    assert_eq!(game_state.wanted_items(RequestItem::Radar), 0);
+    assert_eq!(game_state.wanted_items(RequestItem::Trap), 0);
+    assert!(game_state.plan_pickups().is_empty());
+  }
+
+  #[test]
+  fn radar_carriers_released_with_enough_ore() {
+    let mut game_state = game_with_miners();
+    game_state.miners[0].order = Order::DeployRadarAt(10, 4);
+    game_state.miners[1].order = Order::DeployRadarAt(10, 10);
+    game_state.miners[1].item = Some(Item::Radar);
+
+    for y in 0 .. 4 {
+      game_state.update_cell(20, y, Some(3), false);
+    }
+
+    assert!(game_state.visible_ore_amount() >= game_state.params.min_ore_no_radar_needed);
+    game_state.schedule_items();
+
+    // only the carrier that hasn’t picked its radar up yet gives it up
+    assert_eq!(game_state.miners[0].order.deployed_item(), None);
+    assert_eq!(game_state.miners[1].order, Order::DeployRadarAt(10, 10));
+  }
+
+  #[test]
+  fn flashed_radar_stays_deployed() {
+    let mut game = Game::generate(7);
+    let mut game_state = GameState::new(game.width, game.height, Params::default());
+    let mut strategy = DefaultStrategy;
+    let y = game.robots[0].y;
+    let mut orders = Vec::new();
+    let mut digs = 0;
+
+    // the radar is burried, dug once in vain and left there
+    for turn in 0 .. 8 {
+      read_turn(&mut Cursor::new(game.input(0).as_bytes()), &mut Replay { out: None }, &mut game_state);
+
+      if turn == 0 {
+        game_state.miners[0].order = Order::FlashRadarAt(3, y);
+      }
+
+      let actions = plan_turn(&mut game_state, &mut strategy).iter().map(|request| request.to_string().parse().unwrap()).collect::<Vec<Action>>();
+      orders.push(game_state.miners[0].order);
+      digs += (actions[0] == Action::Dig(3, y)) as usize;
+      game_state.setup_next_turn();
+      game.play_turn([&actions, &[]]);
+    }
+
+    assert!(orders.contains(&Order::RecoverRadarAt(3, y)));
+    assert_ne!(orders.last(), Some(&Order::RecoverRadarAt(3, y)));
+    assert_eq!(digs, 2);
+    assert!(game.burials.iter().any(|burial| burial.owner == 0 && burial.item == referee::Item::Radar && [burial.x, burial.y] == [3, y]));
+  }
+
+  #[test]
+  fn our_burials_are_not_opponent_digs() {
+    let mut game_state = game_with_miners();
+    game_state.update_cell(7, 0, Some(2), false);
+    game_state.setup_next_turn();
+
+    // we burried a radar there and came back with a unit of the cell’s ore
+    game_state.miners[0].burried = Some([7, 0]);
+    game_state.miners[0].item = Some(Item::Ore);
+    game_state.update_cell(7, 0, None, true);
+    game_state.observe_digs();
+    game_state.observe_holes();
+
+    let cell = game_state.known_cell(7, 0).unwrap();
+    assert_eq!(cell.remaining_ore(), Some(1));
+    assert_eq!(cell.source, Some(OreSource::Radar));
+  }
+
+  #[test]
+  fn kamikaze_trades() {
+    let mut game_state = game_with_miners();
+    game_state.burry_trap(100, 6, 3);
+    game_state.miners[0].x = 6;
+    game_state.miners[0].y = 2;
+    game_state.opponent_miners[0].x = 6;
+    game_state.opponent_miners[0].y = 4;
+
+    // the digger dies too, so one opponent isn’t worth it unless the ore is scarce
+    game_state.mode = Mode::Aggressive;
+    assert_eq!(game_state.kamikaze(0), None);
+    game_state.mode = Mode::Denial;
+    assert_eq!(game_state.kamikaze(0), Some(Request::Dig(6, 3)));
+
+    game_state.opponent_miners[1].x = 7;
+    game_state.opponent_miners[1].y = 3;
+    game_state.mode = Mode::Aggressive;
+    assert_eq!(game_state.kamikaze(0), Some(Request::Dig(6, 3)));
+
+    // our traps are left alone when we don’t play them
+    game_state.mode = Mode::Balanced;
+    assert_eq!(game_state.kamikaze(0), None);
+  }
+
+  #[test]
+  fn stops_at_an_old_hole_contest_the_vein() {
+    let mut game_state = game_with_miners();
+    game_state.update_cell(12, 6, Some(3), true);
+    game_state.setup_next_turn();
+
+    // an opponent comes back twice next to the same hole, which doesn’t change
+    for _ in 0 .. CONTESTED_MIN_STOPS {
+      game_state.update_cell(12, 6, Some(3), true);
+      game_state.record_opponent_stop(11, 6);
+      game_state.setup_next_turn();
+    }
+
+    game_state.update_contested_veins();
+
+    assert_eq!(game_state.opponent_stops.len(), CONTESTED_MIN_STOPS);
+    assert!(game_state.contested_veins.contains_key(&[11, 6]));
+
+    // standing still far from any hole isn’t mining
+    game_state.record_opponent_stop(20, 10);
+    assert_eq!(game_state.opponent_stops.len(), CONTESTED_MIN_STOPS);
+  }
+
+  #[test]
+  fn miners_spread_around_a_trap() {
+    let mut game_state = game_with_miners();
+    game_state.burry_trap(100, 10, 5);
+    game_state.miners[0].x = 6;
+    game_state.miners[0].y = 5;
+    game_state.miners[0].order = Order::GoTo(9, 5);
+    game_state.miners[1].x = 11;
+    game_state.miners[1].y = 9;
+    game_state.miners[1].order = Order::DigAt(11, 6, 11, 5);
+
+    // both would end next to the trap; the second one digs from the other side of its cell
+    let mut requests = vec![Request::Move(9, 5).into(), Request::Move(11, 5).into()];
+    game_state.spread_miners(&mut requests);
+
+    assert_eq!(requests[0].req, Request::Move(9, 5));
+    assert_eq!(requests[1].req, Request::Move(11, 6));
+    assert_eq!(game_state.miners[1].order, Order::DigAt(11, 6, 11, 6));
+    assert!(game_state.events.iter().any(|event| event.kind == EventKind::Spreading { from: [11, 5], to: [11, 6] }));
+  }
+
+  #[test]
+  fn waypoint_detours_around_a_trap() {
+    let game_state = GameState::new(30, 15, Params::default());
+    let traps = [[9, 5]].iter().copied().collect::<HashSet<_>>();
+
+    // going straight would stop next to the trap, one more turn avoids it
+    let [x, y] = game_state.next_waypoint([5, 5], [13, 5], &traps);
+
+    assert!(manh_dist([5, 5], [x, y]) <= MINER_SPEED);
+    assert!(manh_dist([9, 5], [x, y]) > 1);
+    assert!(manh_dist([x, y], [13, 5]) <= 2 * MINER_SPEED);
+  }
+
+  #[test]
+  fn waypoint_goes_straight_without_detour() {
+    let game_state = GameState::new(30, 15, Params::default());
+    let wall = (0 .. 15).flat_map(|y| vec![[9, y], [10, y]]).collect::<HashSet<_>>();
+
+    // every path stops next to a trap at least once
+    assert_eq!(game_state.next_waypoint([5, 5], [13, 5], &wall), [13, 5]);
+
+    // nothing to avoid, or the destination is at hand
+    assert_eq!(game_state.next_waypoint([5, 5], [13, 5], &HashSet::new()), [13, 5]);
+    assert_eq!(game_state.next_waypoint([8, 4], [9, 6], &wall), [9, 6]);
+  }
+
+  #[test]
+  fn waypoint_stays_at_destination() {
+    let game_state = GameState::new(30, 15, Params::default());
+    let traps = [[9, 5]].iter().copied().collect::<HashSet<_>>();
+
+    assert_eq!(game_state.next_waypoint([13, 5], [13, 5], &traps), [13, 5]);
+    assert_eq!(game_state.next_waypoint([8, 5], [8, 5], &traps), [8, 5]);
+  }
+
+  #[test]
+  fn dead_miner_gets_no_pickup() {
+    let mut game_state = game_with_miners();
+    game_state.miners[1].x = 20;
+
+    // the dead miner would be the first one at HQ
+    kill(&mut game_state, 0);
+    game_state.allocate_roles();
+    game_state.schedule_items();
+
+    assert_eq!(game_state.miners[0].role, Role::Prospector);
+    assert_eq!(game_state.miners[0].order, Order::DEAD);
+    assert_eq!(game_state.miners[1].role, Role::RadarRunner);
+    assert_eq!(game_state.miners[1].order.deployed_item(), Some(RequestItem::Radar));
+  }
+
+  #[test]
+  fn dead_radar_carrier_is_replaced() {
+    let mut game_state = game_with_miners();
+    game_state.miners[0].order = Order::DeployRadarAt(10, 4);
+    game_state.miners[0].role = Role::RadarRunner;
+    game_state.miners[0].item = Some(Item::Radar);
+
+    kill(&mut game_state, 0);
+    game_state.allocate_roles();
+    game_state.schedule_items();
+
+    assert_eq!(game_state.carriers_count(RequestItem::Radar), 1);
+    assert_eq!(game_state.miners[1].order.deployed_item(), Some(RequestItem::Radar));
+  }
+
+
+  #[test]
+  fn v04_sends_the_miner_closest_to_hq_for_a_radar() {
+    let mut game_state = game_with_miners();
+    game_state.miners[1].x = 3;
+    game_state.miners[1].order = Order::Stay(3, 2);
+
+    let requests = V04Strategy.decide(&mut game_state);
+
+    assert!(matches!(game_state.miners[1].order, Order::DeployRadarAt(..)));
+    assert_eq!(game_state.miners[1].role, Role::RadarRunner);
+    assert_eq!(game_state.miners[0].order.deployed_item(), None);
+    assert_eq!(requests[1].req, Request::Move(0, 2));
+
+    // a single radar carrier at a time
+    V04Strategy.decide(&mut game_state);
+    assert_eq!(game_state.carriers_count(RequestItem::Radar), 1);
+  }
+
+
+  #[test]
+  fn grid_dump_shows_what_we_know() {
+    let mut game_state = game_with_miners();
+    game_state.miners[1].x = 6;
+    game_state.miners[1].order = Order::DigAt(7, 2, 6, 2);
+    game_state.opponent_miners[1].y = 4;
+    game_state.update_cell(7, 1, Some(3), true);
+    game_state.update_cell(8, 1, Some(2), false);
+    game_state.burry_radar(10, 8, 1);
+    game_state.burry_trap(11, 9, 1);
+    game_state.update_cell(10, 1, None, true);
+    game_state.mark_dangerous(10, 1);
+
+    let dump = game_state.grid_dump();
+    let lines = dump.lines().collect::<Vec<_>>();
+    let cell = |x: usize, y: usize| &lines[3 + y][4 + 4 * x .. 7 + 4 * x];
+
+    assert_eq!(lines.len(), 3 + game_state.height);
+    assert!(lines[0].starts_with("turn 0 "));
+    assert_eq!(&lines[1][3 .. 11], "   0   1");
+    assert_eq!(&lines[2][3 + 4 * 3 .. 3 + 4 * 9], " ~~~".repeat(5) + "    ");
+    assert_eq!(&lines[3][.. 3], " 0 ");
+    assert_eq!(cell(5, 0), "0s ");
+    assert_eq!(cell(6, 2), "1d ");
+    assert_eq!(cell(5, 4), "E2 ");
+    assert_eq!(cell(7, 1), "o 3");
+    assert_eq!(cell(8, 1), "R 2");
+    assert_eq!(cell(9, 1), "T  ");
+    assert_eq!(cell(10, 1), "!  ");
+  }
+
+
+  #[test]
+  fn dig_comments_describe_the_dug_cell() {
+    let mut game_state = game_with_miners();
+    game_state.miners[0].order = Order::DigAt(6, 0, 5, 0);
+    game_state.update_cell(6, 0, Some(2), false);
+
+    let requests = carry_orders_out(&mut game_state, false);
+
+    assert_eq!(requests[0].req, Request::Dig(6, 0));
+    assert!(matches!(game_state.miners[0].order, Order::Deliver(..)));
+    assert!(requests[0].comment.as_deref().unwrap().contains("Pd 6,0 o2.0 "), "{}", requests[0]);
+  }
+
+  #[test]
+  fn comments_are_limited() {
+    let request = || Request::Wait.comment("Pd 12,4 o1.5 d0");
+
+    assert_eq!(request().limited(6).to_string(), "WAIT Pd 12,");
+    assert_eq!(request().limited(100).to_string(), "WAIT Pd 12,4 o1.5 d0");
+    assert_eq!(request().limited(0).to_string(), "WAIT");
+    assert_eq!(RequestComment::from(Request::Wait).limited(6).comment, None);
   }
 }
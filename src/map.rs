@@ -0,0 +1,230 @@
+//! Maps: where the ore is.
+//!
+//! Maps are either generated from a seed, the way the official referee does it, or loaded from a
+//! text file to replay a given layout. In a text file, every line is a row of the map and every
+//! whitespace-separated word the ore of a cell, `.` standing for no ore:
+//!
+//! ```text
+//! . . . 2 . .
+//! . . 1 3 1 .
+//! . . . 1 . 2
+//! ```
+//!
+//! The first column is HQ, which never has ore: maps with ore there are rejected.
+//!
+//! Maps are written back in that format.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::referee::{HEIGHT, WIDTH};
+
+/// Minimum number of veins, per cell of the map.
+const VEINS_PER_CELL_MIN: f64 = 0.032;
+
+/// Maximum number of veins, per cell of the map.
+const VEINS_PER_CELL_MAX: f64 = 0.064;
+
+/// Maximum side of the square a vein is spread on.
+const VEIN_SIZE_MAX: i32 = 5;
+
+/// Exponent skewing the vein columns away from HQ: the smaller, the further.
+const VEIN_COLUMN_SKEW: f64 = 0.55;
+
+/// Columns next to HQ never holding the center of a vein.
+const VEIN_MIN_COLUMN: i32 = 4;
+
+/// Minimum amount of ore of a cell of a vein.
+const CELL_ORE_MIN: u32 = 1;
+
+/// Maximum amount of ore of a cell of a vein.
+const CELL_ORE_MAX: u32 = 3;
+
+/// Number of attempts to place the center of a vein on a cell without ore before giving up.
+const VEIN_PLACEMENT_TRIES: usize = 1000;
+
+/// Ore of every cell of a map.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Map {
+  pub width: usize,
+  pub height: usize,
+  /// Ore of every cell, indexed by `y * width + x`.
+  pub ore: Vec<u32>,
+}
+
+impl Map {
+  /// Generate a standard-sized map.
+  pub fn standard(seed: u64) -> Self {
+    Map::generate(WIDTH, HEIGHT, seed)
+  }
+
+  /// Generate a map of any size.
+  ///
+  /// Ore comes in veins: squares of random sizes in which cells get some ore with a probability
+  /// decreasing away from the center. Vein centers are placed at random, skewed away from HQ, so
+  /// that the density of ore increases with the distance to HQ. HQ never has ore.
+  pub fn generate(width: usize, height: usize, seed: u64) -> Self {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut map = Map { width, height, ore: vec![0; width * height] };
+
+    if width < 2 || height == 0 {
+      return map;
+    }
+
+    let cells = (width * height) as f64;
+    let veins_min = ((cells * VEINS_PER_CELL_MIN) as usize).max(1);
+    let veins_max = ((cells * VEINS_PER_CELL_MAX) as usize).max(veins_min);
+    let mut veins = rng.gen_range(veins_min, veins_max + 1);
+    let min_column = VEIN_MIN_COLUMN.min(width as i32 - 1);
+
+    for _ in 0 .. VEIN_PLACEMENT_TRIES {
+      if veins == 0 {
+        break;
+      }
+
+      let skew = rng.gen::<f64>().powf(VEIN_COLUMN_SKEW);
+      let x = min_column + (skew * (width as i32 - min_column) as f64) as i32;
+      let x = x.min(width as i32 - 1);
+      let y = rng.gen_range(0, height as i32);
+
+      if map.ore_at(x, y) > 0 {
+        continue;
+      }
+
+      let size = rng.gen_range(1, VEIN_SIZE_MAX + 1);
+
+      for i in 0 .. size {
+        for j in 0 .. size {
+          let cx = x + i - size / 2;
+          let cy = y + j - size / 2;
+          let distance = (cx - x).abs() + (cy - y).abs();
+          let chance = 100 - distance * 100 / size;
+
+          if cx > 0 && rng.gen_range(0, 100) < chance {
+            if let Some(index) = map.index(cx, cy) {
+              map.ore[index] = rng.gen_range(CELL_ORE_MIN, CELL_ORE_MAX + 1);
+            }
+          }
+        }
+      }
+
+      veins -= 1;
+    }
+
+    map
+  }
+
+  /// Load a map from a text file.
+  pub fn load<P>(path: P) -> io::Result<Self> where P: AsRef<Path> {
+    fs::read_to_string(path)?.parse()
+  }
+
+  fn index(&self, x: i32, y: i32) -> Option<usize> {
+    if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+      Some(y as usize * self.width + x as usize)
+    } else {
+      None
+    }
+  }
+
+  /// Amount of ore of a cell.
+  pub fn ore_at(&self, x: i32, y: i32) -> u32 {
+    self.index(x, y).map(|i| self.ore[i]).unwrap_or(0)
+  }
+
+  /// Total amount of ore of the map.
+  pub fn total_ore(&self) -> u32 {
+    self.ore.iter().sum()
+  }
+}
+
+impl std::str::FromStr for Map {
+  type Err = io::Error;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let rows = text.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<_>>();
+    let width = rows.first().map(|row| row.split_whitespace().count()).unwrap_or(0);
+    let mut ore = Vec::with_capacity(width * rows.len());
+
+    if width == 0 {
+      return Err(invalid("empty map".to_owned()));
+    }
+
+    for (y, row) in rows.iter().enumerate() {
+      let cells = row.split_whitespace().collect::<Vec<_>>();
+
+      if cells.len() != width {
+        return Err(invalid(format!("row {} has {} cells instead of {}", y, cells.len(), width)));
+      }
+
+      for (x, cell) in cells.into_iter().enumerate() {
+        let amount = match cell {
+          "." => 0,
+          _ => cell.parse().map_err(|_| invalid(format!("invalid ore amount {} on row {}", cell, y)))?
+        };
+
+        if x == 0 && amount > 0 {
+          return Err(invalid(format!("ore in HQ on row {}", y)));
+        }
+
+        ore.push(amount);
+      }
+    }
+
+    Ok(Map { width, height: rows.len(), ore })
+  }
+}
+
+impl fmt::Display for Map {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    for row in self.ore.chunks(self.width.max(1)) {
+      let cells = row.iter().map(|&ore| if ore == 0 { ".".to_owned() } else { ore.to_string() }).collect::<Vec<_>>();
+      writeln!(f, "{}", cells.join(" "))?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn same_seed_same_map() {
+    assert_eq!(Map::standard(42), Map::standard(42));
+    assert_ne!(Map::standard(42), Map::standard(43));
+  }
+
+  #[test]
+  fn generated_maps_have_no_ore_in_hq() {
+    for seed in 0 .. 20 {
+      let map = Map::standard(seed);
+
+      assert!(map.total_ore() > 0);
+      assert!((0 .. map.height as i32).all(|y| map.ore_at(0, y) == 0), "seed {}", seed);
+    }
+  }
+
+  #[test]
+  fn text_format_round_trips() {
+    let map = Map::standard(7);
+    let text = map.to_string();
+
+    assert_eq!(text.parse::<Map>().unwrap(), map);
+    assert_eq!(". . 2\n. 1 .\n".parse::<Map>().unwrap().ore, vec![0, 0, 2, 0, 1, 0]);
+  }
+
+  #[test]
+  fn invalid_maps_are_rejected() {
+    assert!("".parse::<Map>().is_err());
+    assert!(". 1\n.\n".parse::<Map>().is_err());
+    assert!(". x\n".parse::<Map>().is_err());
+    assert!(". .\n1 .\n".parse::<Map>().is_err(), "ore in HQ");
+  }
+}
@@ -0,0 +1,159 @@
+//! Players running as external processes, speaking the CodinGame protocol on stdin/stdout.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::referee::Action;
+
+/// How to start a player.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlayerCommand {
+  pub program: String,
+  pub args: Vec<String>,
+  pub env: Vec<(String, String)>,
+}
+
+impl PlayerCommand {
+  /// Command from a whitespace-separated command line, e.g. `./bot prospect`, possibly preceded by
+  /// environment variables, e.g. `UTG_REPLAY=game.replay ./bot`.
+  pub fn parse(command_line: &str) -> Option<Self> {
+    let mut words = command_line.split_whitespace().map(str::to_owned).peekable();
+    let mut env = Vec::new();
+
+    while let Some((key, value)) = words.peek().and_then(|word| word.split_once('=')) {
+      env.push((key.to_owned(), value.to_owned()));
+      words.next();
+    }
+
+    words.next().map(|program| PlayerCommand {
+      program,
+      args: words.collect(),
+      env,
+    })
+  }
+
+  /// Same command, run with an additional environment variable.
+  pub fn with_env<K, V>(mut self, key: K, value: V) -> Self where K: Into<String>, V: Into<String> {
+    self.env.push((key.into(), value.into()));
+    self
+  }
+}
+
+impl fmt::Display for PlayerCommand {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    for (key, value) in &self.env {
+      write!(f, "{}={} ", key, value)?;
+    }
+
+    write!(f, "{}", self.program)?;
+
+    for arg in &self.args {
+      write!(f, " {}", arg)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Why a player failed to answer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlayerError {
+  /// The player didn’t answer in time.
+  Timeout,
+  /// The player stopped, or its pipes broke.
+  Crashed(String),
+  /// The player answered something that isn’t an action.
+  Invalid(String),
+}
+
+impl fmt::Display for PlayerError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      PlayerError::Timeout => f.write_str("timeout"),
+      PlayerError::Crashed(ref reason) => write!(f, "crashed: {}", reason),
+      PlayerError::Invalid(ref line) => write!(f, "invalid output: {}", line),
+    }
+  }
+}
+
+impl From<io::Error> for PlayerError {
+  fn from(err: io::Error) -> Self {
+    PlayerError::Crashed(err.to_string())
+  }
+}
+
+/// A running player.
+///
+/// Its output is read on a separate thread so that it can be waited for with a timeout. The
+/// process is killed when the player is dropped.
+#[derive(Debug)]
+pub struct Player {
+  child: Child,
+  stdin: ChildStdin,
+  lines: Receiver<io::Result<String>>,
+}
+
+impl Player {
+  /// Start a player; what it writes on stderr is discarded.
+  pub fn spawn(command: &PlayerCommand) -> io::Result<Self> {
+    let mut child = Command::new(&command.program)
+      .args(&command.args)
+      .envs(command.env.iter().map(|(key, value)| (key, value)))
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()?;
+
+    let stdin = child.stdin.take().unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    let (sender, lines) = channel();
+
+    thread::spawn(move || {
+      for line in stdout.lines() {
+        if sender.send(line).is_err() {
+          break;
+        }
+      }
+    });
+
+    Ok(Player { child, stdin, lines })
+  }
+
+  /// Send some input.
+  pub fn send(&mut self, input: &str) -> io::Result<()> {
+    self.stdin.write_all(input.as_bytes())?;
+    self.stdin.flush()
+  }
+
+  /// Read a line of output, without its line break, if it comes before a deadline.
+  pub fn read_line(&mut self, deadline: Instant) -> Result<String, PlayerError> {
+    let timeout = deadline.saturating_duration_since(Instant::now());
+
+    match self.lines.recv_timeout(timeout) {
+      Ok(line) => Ok(line?.trim_end().to_owned()),
+      Err(RecvTimeoutError::Timeout) => Err(PlayerError::Timeout),
+      Err(RecvTimeoutError::Disconnected) => Err(PlayerError::Crashed("the player stopped answering".to_owned())),
+    }
+  }
+
+  /// Read the actions of a given number of robots, all of them within a given time.
+  pub fn read_actions(&mut self, count: usize, timeout: Duration) -> Result<Vec<Action>, PlayerError> {
+    let deadline = Instant::now() + timeout;
+
+    (0 .. count).map(|_| {
+      let line = self.read_line(deadline)?;
+      line.parse().map_err(|_| PlayerError::Invalid(line))
+    }).collect()
+  }
+}
+
+impl Drop for Player {
+  fn drop(&mut self) {
+    let _ = self.child.kill();
+    let _ = self.child.wait();
+  }
+}
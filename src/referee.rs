@@ -0,0 +1,552 @@
+//! Rules engine.
+//!
+//! The game state has full information; every player is given the view the official referee
+//! would give it.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::map::Map;
+use crate::view::PlayerView;
+
+/// Default width of the map.
+pub const WIDTH: usize = 30;
+
+/// Default height of the map.
+pub const HEIGHT: usize = 15;
+
+/// Number of robots each player starts with.
+pub const ROBOTS_PER_PLAYER: usize = 5;
+
+/// Number of turns a game lasts.
+pub const MAX_TURNS: u32 = 200;
+
+/// Number of cells a robot can travel in a single turn.
+pub const ROBOT_SPEED: i32 = 4;
+
+/// Cooldown applied to an item once it has been requested.
+pub const ITEM_COOLDOWN: u32 = 5;
+
+/// Distance up to which a radar reveals the ore.
+pub const RADAR_RANGE: i32 = 4;
+
+/// Compute the “Manhattan distance” between two points.
+pub fn manh_dist(a: [i32; 2], b: [i32; 2]) -> i32 {
+  (a[0] - b[0]).abs() + (a[1] - b[1]).abs()
+}
+
+/// Item a robot can carry or burry.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Item {
+  Radar,
+  Trap,
+  Ore,
+}
+
+impl Item {
+  /// Code of the item in the protocol.
+  pub fn code(self) -> i32 {
+    match self {
+      Item::Radar => 2,
+      Item::Trap => 3,
+      Item::Ore => 4,
+    }
+  }
+}
+
+/// Action of a single robot.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+  Wait,
+  Move(i32, i32),
+  Dig(i32, i32),
+  Request(Item),
+}
+
+impl fmt::Display for Action {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      Action::Wait => f.write_str("WAIT"),
+      Action::Move(x, y) => write!(f, "MOVE {} {}", x, y),
+      Action::Dig(x, y) => write!(f, "DIG {} {}", x, y),
+      Action::Request(Item::Radar) => f.write_str("REQUEST RADAR"),
+      Action::Request(Item::Trap) => f.write_str("REQUEST TRAP"),
+      Action::Request(Item::Ore) => f.write_str("REQUEST ORE"),
+    }
+  }
+}
+
+/// Error raised when an output line isn’t a valid action.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvalidAction(pub String);
+
+impl fmt::Display for InvalidAction {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "invalid action: {}", self.0)
+  }
+}
+
+impl FromStr for Action {
+  type Err = InvalidAction;
+
+  /// Parse an output line; anything after the action is a comment.
+  fn from_str(line: &str) -> Result<Self, Self::Err> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let invalid = || InvalidAction(line.trim().to_owned());
+    let coords = |words: &[&str]| -> Result<(i32, i32), InvalidAction> {
+      match words {
+        [x, y, ..] => Ok((x.parse().map_err(|_| invalid())?, y.parse().map_err(|_| invalid())?)),
+        _ => Err(invalid())
+      }
+    };
+
+    match words.split_first() {
+      Some((&"WAIT", _)) => Ok(Action::Wait),
+      Some((&"MOVE", rest)) => coords(rest).map(|(x, y)| Action::Move(x, y)),
+      Some((&"DIG", rest)) => coords(rest).map(|(x, y)| Action::Dig(x, y)),
+      Some((&"REQUEST", rest)) => match rest.first() {
+        Some(&"RADAR") => Ok(Action::Request(Item::Radar)),
+        Some(&"TRAP") => Ok(Action::Request(Item::Trap)),
+        _ => Err(invalid())
+      },
+      _ => Err(invalid())
+    }
+  }
+}
+
+/// A robot of either player.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Robot {
+  pub id: u32,
+  pub owner: usize,
+  pub x: i32,
+  pub y: i32,
+  pub item: Option<Item>,
+  pub alive: bool,
+}
+
+/// A radar or a trap burried by a player.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Burial {
+  pub id: u32,
+  pub owner: usize,
+  pub item: Item,
+  pub x: i32,
+  pub y: i32,
+}
+
+/// Full-information state of a game.
+#[derive(Clone, Debug)]
+pub struct Game {
+  pub width: usize,
+  pub height: usize,
+  pub ore: Vec<u32>,
+  pub holes: Vec<bool>,
+  pub robots: Vec<Robot>,
+  pub burials: Vec<Burial>,
+  pub scores: [u32; 2],
+  pub radar_cooldowns: [u32; 2],
+  pub trap_cooldowns: [u32; 2],
+  pub turn: u32,
+  next_id: u32,
+}
+
+impl Game {
+  /// Start a game on a given map; `ore` is indexed by `y * width + x`.
+  ///
+  /// Robots start in HQ, on the same rows for both players.
+  pub fn new<R>(width: usize, height: usize, ore: Vec<u32>, rng: &mut R) -> Self where R: Rng {
+    assert_eq!(ore.len(), width * height, "the ore must cover the whole map");
+
+    let mut rows = (0 .. height as i32).collect::<Vec<_>>();
+    for i in (1 .. rows.len()).rev() {
+      rows.swap(i, rng.gen_range(0, i + 1));
+    }
+
+    let mut robots = Vec::new();
+    for owner in 0 .. 2 {
+      for i in 0 .. ROBOTS_PER_PLAYER {
+        robots.push(Robot {
+          id: robots.len() as u32,
+          owner,
+          x: 0,
+          y: rows[i % rows.len()],
+          item: None,
+          alive: true,
+        });
+      }
+    }
+
+    Game {
+      width,
+      height,
+      ore,
+      holes: vec![false; width * height],
+      next_id: robots.len() as u32,
+      robots,
+      burials: Vec::new(),
+      scores: [0, 0],
+      radar_cooldowns: [0, 0],
+      trap_cooldowns: [0, 0],
+      turn: 0,
+    }
+  }
+
+  /// Start a game on a randomly generated standard map.
+  pub fn generate(seed: u64) -> Self {
+    Game::on_map(Map::standard(seed), seed)
+  }
+
+  /// Start a game on a given map, the starting rows of the robots drawn from a seed.
+  pub fn on_map(map: Map, seed: u64) -> Self {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    Game::new(map.width, map.height, map.ore, &mut rng)
+  }
+
+  fn index(&self, x: i32, y: i32) -> Option<usize> {
+    if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+      Some(y as usize * self.width + x as usize)
+    } else {
+      None
+    }
+  }
+
+  /// Amount of ore left in a cell.
+  pub fn ore_at(&self, x: i32, y: i32) -> u32 {
+    self.index(x, y).map(|i| self.ore[i]).unwrap_or(0)
+  }
+
+  /// Whether a cell has a hole.
+  pub fn hole_at(&self, x: i32, y: i32) -> bool {
+    self.index(x, y).map(|i| self.holes[i]).unwrap_or(false)
+  }
+
+  /// Robots of a player, in the order their actions are expected.
+  pub fn robots_of(&self, player: usize) -> impl Iterator<Item = &Robot> {
+    self.robots.iter().filter(move |robot| robot.owner == player)
+  }
+
+  /// Whether the game is over: no turns left, no robots left, or no ore left to score.
+  pub fn is_over(&self) -> bool {
+    let no_robots = self.robots.iter().all(|robot| !robot.alive);
+    let no_ore = self.ore.iter().all(|&ore| ore == 0) && self.robots.iter().all(|robot| robot.item != Some(Item::Ore));
+
+    self.turn >= MAX_TURNS || no_robots || no_ore
+  }
+
+  /// Initialization input, given once at the beginning of the game.
+  pub fn init_input(&self) -> String {
+    format!("{} {}\n", self.width, self.height)
+  }
+
+  /// Input of a player for the current turn, with the fog of war applied (see `PlayerView`).
+  pub fn input(&self, player: usize) -> String {
+    PlayerView::fog(self, player).to_string()
+  }
+
+  /// Play a turn given the actions of both players’ robots.
+  ///
+  /// Digs are resolved first (traps blowing up, items being burried then ore extracted), then item
+  /// requests, then moves; robots in HQ deliver their ore at the end of the turn.
+  pub fn play_turn(&mut self, actions: [&[Action]; 2]) {
+    let mut robot_actions = Vec::new();
+
+    for (player, &actions) in actions.iter().enumerate() {
+      let robots = self.robots.iter().enumerate().filter(|(_, robot)| robot.owner == player).map(|(i, _)| i).collect::<Vec<_>>();
+
+      for (robot_index, &action) in robots.into_iter().zip(actions) {
+        if self.robots[robot_index].alive {
+          robot_actions.push((robot_index, action));
+        }
+      }
+    }
+
+    self.resolve_digs(&robot_actions);
+    self.resolve_requests(&robot_actions);
+    self.resolve_moves(&robot_actions);
+
+    for robot in &mut self.robots {
+      if robot.alive && robot.x == 0 && robot.item == Some(Item::Ore) {
+        robot.item = None;
+        self.scores[robot.owner] += 1;
+      }
+    }
+
+    for cooldown in self.radar_cooldowns.iter_mut().chain(self.trap_cooldowns.iter_mut()) {
+      *cooldown = cooldown.saturating_sub(1);
+    }
+
+    self.turn += 1;
+  }
+
+  /// Digs of robots next to their target; traps of dug cells blow up first.
+  fn resolve_digs(&mut self, actions: &[(usize, Action)]) {
+    let digs = actions.iter().filter_map(|&(robot_index, action)| {
+      let robot = &self.robots[robot_index];
+
+      match action {
+        Action::Dig(x, y) if x > 0 && self.index(x, y).is_some() && manh_dist([robot.x, robot.y], [x, y]) <= 1 => {
+          Some((robot_index, [x, y]))
+        }
+
+        _ => None
+      }
+    }).collect::<Vec<_>>();
+
+    // traps blow up in chain
+    let mut explosions = digs.iter()
+      .filter(|(_, cell)| self.burials.iter().any(|burial| burial.item == Item::Trap && [burial.x, burial.y] == *cell))
+      .map(|&(_, cell)| cell)
+      .collect::<Vec<_>>();
+
+    while let Some(cell) = explosions.pop() {
+      let exploded = self.burials.iter()
+        .position(|burial| burial.item == Item::Trap && [burial.x, burial.y] == cell);
+
+      if let Some(exploded) = exploded {
+        self.burials.remove(exploded);
+      }
+
+      for robot in &mut self.robots {
+        if robot.alive && manh_dist([robot.x, robot.y], cell) <= 1 {
+          robot.alive = false;
+          robot.item = None;
+        }
+      }
+
+      explosions.extend(self.burials.iter()
+        .filter(|burial| burial.item == Item::Trap && manh_dist([burial.x, burial.y], cell) <= 1)
+        .map(|burial| [burial.x, burial.y]));
+    }
+
+    for (robot_index, [x, y]) in digs {
+      if !self.robots[robot_index].alive {
+        continue;
+      }
+
+      let owner = self.robots[robot_index].owner;
+      let index = self.index(x, y).unwrap();
+      self.holes[index] = true;
+
+      // digging destroys the opponent radars; as in the official referee, our own radars are never
+      // given back
+      self.burials.retain(|burial| !(burial.owner != owner && burial.item == Item::Radar && [burial.x, burial.y] == [x, y]));
+
+      // as in the official referee, the item carried is burried (ore going back into the cell)
+      // before the ore of the cell is collected
+      match self.robots[robot_index].item.take() {
+        Some(item @ Item::Radar) | Some(item @ Item::Trap) => {
+          self.burials.push(Burial { id: self.next_id, owner, item, x, y });
+          self.next_id += 1;
+        }
+
+        Some(Item::Ore) => self.ore[index] += 1,
+        None => ()
+      }
+
+      if self.ore[index] > 0 {
+        self.ore[index] -= 1;
+        self.robots[robot_index].item = Some(Item::Ore);
+      }
+    }
+  }
+
+  /// Item requests of robots in HQ, granted if the item is available.
+  fn resolve_requests(&mut self, actions: &[(usize, Action)]) {
+    for &(robot_index, action) in actions {
+      let robot = &self.robots[robot_index];
+
+      if let Action::Request(item) = action {
+        if !robot.alive || robot.x != 0 {
+          continue;
+        }
+
+        let owner = robot.owner;
+        let cooldown = match item {
+          Item::Radar => &mut self.radar_cooldowns[owner],
+          Item::Trap => &mut self.trap_cooldowns[owner],
+          Item::Ore => continue,
+        };
+
+        if *cooldown == 0 {
+          *cooldown = ITEM_COOLDOWN;
+          self.robots[robot_index].item = Some(item);
+        }
+      }
+    }
+  }
+
+  /// Moves, and digs of robots too far from their target; robots travel horizontally first.
+  fn resolve_moves(&mut self, actions: &[(usize, Action)]) {
+    for &(robot_index, action) in actions {
+      let (target, range) = match action {
+        Action::Move(x, y) => ([x, y], 0),
+        Action::Dig(x, y) => ([x, y], 1),
+        _ => continue
+      };
+
+      let width = self.width as i32;
+      let height = self.height as i32;
+      let robot = &mut self.robots[robot_index];
+
+      if !robot.alive {
+        continue;
+      }
+
+      let target = [target[0].max(0).min(width - 1), target[1].max(0).min(height - 1)];
+
+      for _ in 0 .. ROBOT_SPEED {
+        if manh_dist([robot.x, robot.y], target) <= range {
+          break;
+        }
+
+        if robot.x != target[0] {
+          robot.x += (target[0] - robot.x).signum();
+        } else {
+          robot.y += (target[1] - robot.y).signum();
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Game on an empty map, every robot waiting far away from the others.
+  fn empty_game() -> Game {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut game = Game::new(WIDTH, HEIGHT, vec![0; WIDTH * HEIGHT], &mut rng);
+
+    for (i, robot) in game.robots.iter_mut().enumerate() {
+      robot.x = 20;
+      robot.y = i as i32;
+    }
+
+    game
+  }
+
+  fn place(game: &mut Game, robot: usize, x: i32, y: i32) {
+    game.robots[robot].x = x;
+    game.robots[robot].y = y;
+  }
+
+  fn burry(game: &mut Game, owner: usize, item: Item, x: i32, y: i32) {
+    game.burials.push(Burial { id: game.next_id, owner, item, x, y });
+    game.next_id += 1;
+  }
+
+  #[test]
+  fn traps_blow_up_in_chain() {
+    let mut game = empty_game();
+    burry(&mut game, 1, Item::Trap, 5, 5);
+    burry(&mut game, 1, Item::Trap, 6, 5);
+    burry(&mut game, 1, Item::Trap, 8, 5);
+    place(&mut game, 0, 4, 5);
+    place(&mut game, 5, 7, 5);
+    place(&mut game, 1, 9, 5);
+
+    game.play_turn([&[Action::Dig(5, 5)], &[]]);
+
+    assert!(!game.robots[0].alive);
+    assert!(!game.robots[5].alive);
+    assert!(game.robots[1].alive);
+    assert_eq!(game.burials.iter().map(|burial| [burial.x, burial.y]).collect::<Vec<_>>(), vec![[8, 5]]);
+    assert!(!game.hole_at(5, 5), "a destroyed robot doesn’t dig");
+  }
+
+  #[test]
+  fn digs_burry_the_carried_item() {
+    let mut game = empty_game();
+    game.ore[5 * WIDTH + 5] = 2;
+    place(&mut game, 0, 5, 4);
+    game.robots[0].item = Some(Item::Radar);
+
+    game.play_turn([&[Action::Dig(5, 5)], &[]]);
+
+    assert!(game.hole_at(5, 5));
+    assert_eq!(game.burials.len(), 1);
+    assert_eq!([game.burials[0].owner, game.burials[0].x as usize, game.burials[0].y as usize], [0, 5, 5]);
+    assert_eq!(game.burials[0].item, Item::Radar);
+    assert_eq!(game.robots[0].item, Some(Item::Ore));
+    assert_eq!(game.ore_at(5, 5), 1);
+  }
+
+  #[test]
+  fn carried_ore_goes_back_before_collecting() {
+    let mut game = empty_game();
+    place(&mut game, 0, 5, 5);
+    game.robots[0].item = Some(Item::Ore);
+
+    game.play_turn([&[Action::Dig(5, 5)], &[]]);
+
+    assert_eq!(game.robots[0].item, Some(Item::Ore));
+    assert_eq!(game.ore_at(5, 5), 0);
+  }
+
+  #[test]
+  fn digs_destroy_opponent_radars_only() {
+    let mut game = empty_game();
+    burry(&mut game, 1, Item::Radar, 5, 5);
+    burry(&mut game, 0, Item::Radar, 6, 6);
+    place(&mut game, 0, 5, 5);
+    place(&mut game, 1, 6, 6);
+
+    game.play_turn([&[Action::Dig(5, 5), Action::Dig(6, 6)], &[]]);
+
+    assert_eq!(game.burials.iter().map(|burial| (burial.owner, [burial.x, burial.y])).collect::<Vec<_>>(), vec![(0, [6, 6])]);
+    assert_eq!(game.robots[1].item, None, "our own radar isn’t given back");
+  }
+
+  #[test]
+  fn requests_follow_the_cooldowns() {
+    let mut game = empty_game();
+    place(&mut game, 0, 0, 0);
+    place(&mut game, 1, 0, 1);
+    place(&mut game, 2, 1, 2);
+    place(&mut game, 5, 0, 5);
+    let requests = [Action::Request(Item::Radar), Action::Request(Item::Radar), Action::Request(Item::Trap)];
+
+    game.play_turn([&requests, &[Action::Request(Item::Radar)]]);
+
+    assert_eq!(game.robots[0].item, Some(Item::Radar));
+    assert_eq!(game.robots[1].item, None, "the radar is on cooldown");
+    assert_eq!(game.robots[2].item, None, "the robot isn’t in HQ");
+    assert_eq!(game.robots[5].item, Some(Item::Radar), "cooldowns are per player");
+    assert_eq!(game.radar_cooldowns, [ITEM_COOLDOWN - 1; 2]);
+    assert_eq!(game.trap_cooldowns, [0, 0]);
+
+    let retry = [Action::Wait, Action::Request(Item::Radar)];
+
+    for _ in 1 .. ITEM_COOLDOWN {
+      game.play_turn([&retry, &[]]);
+      assert_eq!(game.robots[1].item, None);
+    }
+
+    game.play_turn([&retry, &[]]);
+    assert_eq!(game.robots[1].item, Some(Item::Radar));
+  }
+
+  #[test]
+  fn robots_move_horizontally_first() {
+    let mut game = empty_game();
+    place(&mut game, 0, 0, 0);
+    place(&mut game, 1, 0, 5);
+
+    game.play_turn([&[Action::Move(3, 5), Action::Dig(10, 5)], &[]]);
+
+    assert_eq!([game.robots[0].x, game.robots[0].y], [3, 1]);
+    assert_eq!([game.robots[1].x, game.robots[1].y], [4, 5]);
+    assert!(!game.hole_at(10, 5), "the robot is too far to dig");
+
+    game.play_turn([&[Action::Move(3, 5), Action::Dig(10, 5)], &[]]);
+    game.play_turn([&[Action::Move(3, 5), Action::Dig(10, 5)], &[]]);
+
+    assert_eq!([game.robots[0].x, game.robots[0].y], [3, 5]);
+    assert_eq!([game.robots[1].x, game.robots[1].y], [9, 5], "the robot stops next to the cell");
+  }
+}
@@ -0,0 +1,154 @@
+//! Games recorded by the bot: the input it read, what it answered and what it thought, turn by
+//! turn.
+//!
+//! The bot writes replays with tagged lines (see `Replay` in `main.rs`), so that they can be read
+//! from a dedicated file as well as from a stderr dump mixed with other debug output: lines
+//! without a tag are ignored.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use crate::view::{read_init, PlayerView};
+
+/// Tag of the input lines.
+const INPUT_TAG: &str = "@<";
+
+/// Tag of the output lines.
+const OUTPUT_TAG: &str = "@>";
+
+/// Tag of the event lines.
+const EVENT_TAG: &str = "@#";
+
+/// Tag of the end of a turn, followed by the dangerous cells.
+const END_TAG: &str = "@!";
+
+/// A recorded turn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Turn {
+  /// What the bot was given.
+  pub view: PlayerView,
+  /// What the bot answered, one line per robot, comments included.
+  pub outputs: Vec<String>,
+  /// Events the bot logged, in their compact form.
+  pub events: Vec<String>,
+  /// Cells the bot thought could hold a trap.
+  pub dangerous: Vec<[i32; 2]>,
+}
+
+/// A recorded game.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+  pub width: usize,
+  pub height: usize,
+  pub turns: Vec<Turn>,
+}
+
+fn invalid<E>(err: E) -> io::Error where E: ToString {
+  io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+impl Replay {
+  /// Read a replay from a file.
+  pub fn load<P>(path: P) -> io::Result<Self> where P: AsRef<Path> {
+    Replay::parse(&fs::read_to_string(path)?)
+  }
+
+  /// Read a replay from text.
+  ///
+  /// A last turn the bot didn’t get to the end of, e.g. because it crashed, is kept with the
+  /// outputs it gave, if its input is complete.
+  pub fn parse(text: &str) -> io::Result<Self> {
+    let mut replay = Replay::default();
+    let mut input = String::new();
+    let mut outputs = Vec::new();
+    let mut events = Vec::new();
+
+    for line in text.lines() {
+      if let Some(line) = line.strip_prefix(INPUT_TAG) {
+        input += line;
+        input.push('\n');
+      } else if let Some(line) = line.strip_prefix(OUTPUT_TAG) {
+        outputs.push(line.to_owned());
+      } else if let Some(line) = line.strip_prefix(EVENT_TAG) {
+        events.push(line.to_owned());
+      } else if let Some(line) = line.strip_prefix(END_TAG) {
+        let dangerous = line.split_whitespace().map(|cell| {
+          let (x, y) = cell.split_once(',').ok_or_else(|| invalid(format!("invalid cell {}", cell)))?;
+          Ok([x.parse().map_err(invalid)?, y.parse().map_err(invalid)?])
+        }).collect::<io::Result<_>>()?;
+
+        let view = replay.read_turn(&input)?.ok_or_else(|| invalid(format!("turn {} has no input", replay.turns.len())))?;
+        replay.turns.push(Turn { view, outputs, events, dangerous });
+        input.clear();
+        outputs = Vec::new();
+        events = Vec::new();
+      }
+    }
+
+    if let Ok(Some(view)) = replay.read_turn(&input) {
+      replay.turns.push(Turn { view, outputs, events, dangerous: Vec::new() });
+    }
+
+    Ok(replay)
+  }
+
+  /// Read the input of a turn, preceded by the initialization input for the first one.
+  fn read_turn(&mut self, input: &str) -> io::Result<Option<PlayerView>> {
+    let mut input = Cursor::new(input.as_bytes());
+
+    if self.turns.is_empty() {
+      let (width, height) = read_init(&mut input)?;
+      self.width = width;
+      self.height = height;
+    }
+
+    PlayerView::read(self.width, self.height, &mut input)
+  }
+
+  /// Everything the bot read, to be piped back into it.
+  pub fn input(&self) -> String {
+    let mut input = format!("{} {}\n", self.width, self.height);
+
+    for turn in &self.turns {
+      input += &turn.view.to_string();
+    }
+
+    input
+  }
+
+  /// Everything the bot answered.
+  pub fn output(&self) -> String {
+    self.turns.iter().flat_map(|turn| &turn.outputs).map(|line| format!("{}\n", line)).collect()
+  }
+}
+
+impl fmt::Display for Replay {
+  /// Write the replay as the bot records it.
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    writeln!(f, "{}{} {}", INPUT_TAG, self.width, self.height)?;
+
+    for turn in &self.turns {
+      for line in turn.view.to_string().lines() {
+        writeln!(f, "{}{}", INPUT_TAG, line)?;
+      }
+
+      for line in &turn.outputs {
+        writeln!(f, "{}{}", OUTPUT_TAG, line)?;
+      }
+
+      for line in &turn.events {
+        writeln!(f, "{}{}", EVENT_TAG, line)?;
+      }
+
+      write!(f, "{}", END_TAG)?;
+      for [x, y] in &turn.dangerous {
+        write!(f, " {},{}", x, y)?;
+      }
+      writeln!(f)?;
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,269 @@
+//! What a player sees of the game, as given on its standard input.
+//!
+//! A view is either made from the full-information state of a game, applying the fog of war the
+//! official referee applies, or read from a player input. Its `Display` implementation writes it
+//! exactly as players read it, so both ways round-trip.
+
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::referee::{manh_dist, Game, Item, RADAR_RANGE};
+
+/// Kind of a visible entity.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EntityKind {
+  Robot,
+  OpponentRobot,
+  Radar,
+  Trap,
+}
+
+/// A visible entity.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Entity {
+  pub id: u32,
+  pub kind: EntityKind,
+  pub x: i32,
+  pub y: i32,
+  pub item: Option<Item>,
+}
+
+impl Entity {
+  /// Whether this entity is a destroyed robot.
+  pub fn is_dead(&self) -> bool {
+    self.x == -1 && self.y == -1
+  }
+}
+
+/// Input of a player for a single turn.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlayerView {
+  pub width: usize,
+  pub height: usize,
+  /// Our score first, then the opponent’s.
+  pub scores: [u32; 2],
+  /// Ore of every cell, if under one of our radars; indexed by `y * width + x`.
+  pub ore: Vec<Option<u32>>,
+  pub holes: Vec<bool>,
+  pub radar_cooldown: u32,
+  pub trap_cooldown: u32,
+  pub entities: Vec<Entity>,
+}
+
+fn invalid<E>(err: E) -> io::Error where E: ToString {
+  io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Read a line split in words; `None` at the end of the input.
+fn read_words<R>(input: &mut R) -> io::Result<Option<Vec<String>>> where R: BufRead {
+  let mut line = String::new();
+
+  if input.read_line(&mut line)? == 0 {
+    return Ok(None);
+  }
+
+  Ok(Some(line.split_whitespace().map(str::to_owned).collect()))
+}
+
+/// Read a line split in words, failing at the end of the input.
+fn expect_words<R>(input: &mut R) -> io::Result<Vec<String>> where R: BufRead {
+  read_words(input)?.ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated input"))
+}
+
+/// Parse the word at a given position.
+fn word<T>(words: &[String], index: usize) -> io::Result<T> where T: std::str::FromStr, T::Err: ToString {
+  words.get(index).ok_or_else(|| invalid(format!("missing value in {}", words.join(" "))))?.parse().map_err(invalid)
+}
+
+/// Read the initialization input: the size of the map.
+pub fn read_init<R>(input: &mut R) -> io::Result<(usize, usize)> where R: BufRead {
+  let words = expect_words(input)?;
+
+  Ok((word(&words, 0)?, word(&words, 1)?))
+}
+
+impl PlayerView {
+  /// What a player sees of a game:
+  ///
+  /// - the ore is only known under the player’s radars, shown as `?` elsewhere;
+  /// - holes are visible everywhere;
+  /// - the opponent robots never show their items, shown as -1, and dead robots are at (-1, -1);
+  /// - only the player’s own radars and traps are listed, after the robots.
+  pub fn fog(game: &Game, player: usize) -> Self {
+    let radars = game.burials.iter()
+      .filter(|burial| burial.owner == player && burial.item == Item::Radar)
+      .map(|burial| [burial.x, burial.y])
+      .collect::<Vec<_>>();
+
+    let cells = (0 .. game.height as i32).flat_map(|y| (0 .. game.width as i32).map(move |x| [x, y])).collect::<Vec<_>>();
+    let ore = cells.iter().map(|&[x, y]| {
+      if radars.iter().any(|&radar| manh_dist(radar, [x, y]) <= RADAR_RANGE) {
+        Some(game.ore_at(x, y))
+      } else {
+        None
+      }
+    }).collect();
+    let holes = cells.iter().map(|&[x, y]| game.hole_at(x, y)).collect();
+
+    let robots = game.robots.iter().map(|robot| {
+      let [x, y] = if robot.alive { [robot.x, robot.y] } else { [-1, -1] };
+
+      Entity {
+        id: robot.id,
+        kind: if robot.owner == player { EntityKind::Robot } else { EntityKind::OpponentRobot },
+        x,
+        y,
+        item: if robot.owner == player { robot.item } else { None },
+      }
+    });
+
+    let burials = game.burials.iter().filter(|burial| burial.owner == player).map(|burial| Entity {
+      id: burial.id,
+      kind: if burial.item == Item::Radar { EntityKind::Radar } else { EntityKind::Trap },
+      x: burial.x,
+      y: burial.y,
+      item: None,
+    });
+
+    PlayerView {
+      width: game.width,
+      height: game.height,
+      scores: [game.scores[player], game.scores[1 - player]],
+      ore,
+      holes,
+      radar_cooldown: game.radar_cooldowns[player],
+      trap_cooldown: game.trap_cooldowns[player],
+      entities: robots.chain(burials).collect(),
+    }
+  }
+
+  /// Read the input of a turn; `None` if the game is over.
+  pub fn read<R>(width: usize, height: usize, input: &mut R) -> io::Result<Option<Self>> where R: BufRead {
+    let words = match read_words(input)? {
+      Some(words) if !words.is_empty() => words,
+      _ => return Ok(None)
+    };
+
+    let scores = [word(&words, 0)?, word(&words, 1)?];
+    let mut ore = Vec::with_capacity(width * height);
+    let mut holes = Vec::with_capacity(width * height);
+
+    for _ in 0 .. height {
+      let words = expect_words(input)?;
+
+      for x in 0 .. width {
+        ore.push(word::<String>(&words, 2 * x)?.parse().ok());
+        holes.push(word::<u32>(&words, 2 * x + 1)? == 1);
+      }
+    }
+
+    let words = expect_words(input)?;
+    let count: usize = word(&words, 0)?;
+    let radar_cooldown = word(&words, 1)?;
+    let trap_cooldown = word(&words, 2)?;
+    let mut entities = Vec::with_capacity(count);
+
+    for _ in 0 .. count {
+      let words = expect_words(input)?;
+      let kind = match word::<u32>(&words, 1)? {
+        0 => EntityKind::Robot,
+        1 => EntityKind::OpponentRobot,
+        2 => EntityKind::Radar,
+        3 => EntityKind::Trap,
+        kind => return Err(invalid(format!("unknown entity type {}", kind)))
+      };
+      let item = match word::<i32>(&words, 4)? {
+        2 => Some(Item::Radar),
+        3 => Some(Item::Trap),
+        4 => Some(Item::Ore),
+        _ => None
+      };
+
+      entities.push(Entity {
+        id: word(&words, 0)?,
+        kind,
+        x: word(&words, 2)?,
+        y: word(&words, 3)?,
+        item,
+      });
+    }
+
+    Ok(Some(PlayerView {
+      width,
+      height,
+      scores,
+      ore,
+      holes,
+      radar_cooldown,
+      trap_cooldown,
+      entities,
+    }))
+  }
+
+  fn index(&self, x: i32, y: i32) -> Option<usize> {
+    if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+      Some(y as usize * self.width + x as usize)
+    } else {
+      None
+    }
+  }
+
+  /// Ore of a cell, if we know it.
+  pub fn ore_at(&self, x: i32, y: i32) -> Option<u32> {
+    self.index(x, y).and_then(|i| self.ore[i])
+  }
+
+  /// Whether a cell has a hole.
+  pub fn hole_at(&self, x: i32, y: i32) -> bool {
+    self.index(x, y).map(|i| self.holes[i]).unwrap_or(false)
+  }
+
+  /// Entities of a given kind.
+  pub fn entities_of(&self, kind: EntityKind) -> impl Iterator<Item = &Entity> {
+    self.entities.iter().filter(move |entity| entity.kind == kind)
+  }
+
+  /// Our robots, dead ones included, in the order their actions are expected.
+  pub fn robots(&self) -> impl Iterator<Item = &Entity> {
+    self.entities_of(EntityKind::Robot)
+  }
+}
+
+impl fmt::Display for PlayerView {
+  /// Write the view as the turn input of the player.
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    writeln!(f, "{} {}", self.scores[0], self.scores[1])?;
+
+    for y in 0 .. self.height {
+      for x in 0 .. self.width {
+        let i = y * self.width + x;
+
+        if x != 0 {
+          f.write_str(" ")?;
+        }
+
+        match self.ore[i] {
+          Some(ore) => write!(f, "{} {}", ore, self.holes[i] as u8)?,
+          None => write!(f, "? {}", self.holes[i] as u8)?,
+        }
+      }
+
+      writeln!(f)?;
+    }
+
+    writeln!(f, "{} {} {}", self.entities.len(), self.radar_cooldown, self.trap_cooldown)?;
+
+    for entity in &self.entities {
+      let kind = match entity.kind {
+        EntityKind::Robot => 0,
+        EntityKind::OpponentRobot => 1,
+        EntityKind::Radar => 2,
+        EntityKind::Trap => 3,
+      };
+
+      writeln!(f, "{} {} {} {} {}", entity.id, kind, entity.x, entity.y, entity.item.map(Item::code).unwrap_or(-1))?;
+    }
+
+    Ok(())
+  }
+}